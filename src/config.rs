@@ -1,14 +1,29 @@
-use crate::hooks::event_loop_hook::enforce_global_ime_mode;
+use crate::hooks::event_loop_hook::enforce_ime_mode;
+use crate::ime::pinyin_settings::apply_pinyin_settings;
+use crate::state::{seed_from_legacy_config, update_state, STATE, STATE_FILE_NAME};
 use crate::tray::icon::update_tray_icon;
 use crate::tray::notifications::show_balloon_tip;
-use crate::utils::hot_key::register_all_hotkeys;
+use crate::utils::config_file::{restore_previous, save_with_backup, with_suffix};
+use crate::utils::config_format::{ConfigFormat, FormatError};
+use crate::utils::config_location::config_file_path;
+use crate::utils::config_migration::{migrate, CURRENT_VERSION};
+use crate::utils::config_patch::patch;
+use crate::utils::hot_key::{refresh_hotkeys, register_all_hotkeys};
+use crate::utils::parse_hotkey::{Hotkey, MOD_ALT};
+use crate::utils::trigger::{check_chord_prefixes, Trigger};
+use crate::utils::window_process::process_name_of_window;
 use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use windows::core::PCWSTR;
 use windows::Win32::Foundation::{GetLastError, HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, MessageBoxW, MB_ICONERROR, MB_OK,
+};
 use winreg::enums::*;
 use winreg::RegKey;
 
@@ -24,84 +39,734 @@ impl Default for ImeMode {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub enum EnforceStrategy {
+    #[default]
+    KeyboardLayout, // 切换键盘布局（适用于微软拼音）
+    Keystroke, // 模拟输入法自身的切换键（适用于搜狗、QQ 拼音等）
+}
+
+// 绑定了 CapsLock 时如何保留大小写锁定功能
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum CapsLockMode {
+    #[default]
+    ShiftToggles, // CapsLock 触发动作，Shift+CapsLock 切换大小写锁定
+    LongPressToggles, // 轻按 CapsLock 触发动作，长按切换大小写锁定
+    Native,           // 不做特殊处理，作为普通热键注册
+}
+
+fn default_ime_toggle_keys() -> HashMap<String, String> {
+    HashMap::from([("default".to_string(), "Shift".to_string())])
+}
+
+// 检测输入法被反复切回（与其他程序"拉锯"）时的退避设置
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct OscillationGuard {
+    pub enabled: bool,
+    pub max_enforcements: usize, // 同一窗口在 window_secs 内被强制多少次视为拉锯
+    pub window_secs: u64,
+    pub backoff_secs: u64, // 退避期间不再强制该窗口
+    pub notify: bool,      // 每个程序仅通知一次
+}
+
+impl Default for OscillationGuard {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_enforcements: 5,
+            window_secs: 10,
+            backoff_secs: 60,
+            notify: true,
+        }
+    }
+}
+
+// 微软拼音自身的设置，启动时写入注册表，卸载时还原
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct PinyinSettings {
+    pub default_mode: Option<ImeMode>,     // 新窗口的默认中/英文模式
+    pub shift_toggle: Option<bool>,        // 是否允许 Shift 切换中/英
+    pub ctrl_space_toggle: Option<bool>,   // 是否允许 Ctrl+Space 切换中/英
+    pub raw_values: BTreeMap<String, u32>, // 其他 DWORD 值（注册表值名 -> 值）
+}
+
+// 配置方案：覆盖部分设置，未写出的设置沿用顶层的值。
+// ime_mode / master_switch 在切换到该方案时写入运行状态，其余设置在方案生效期间取代顶层的值
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ime_mode: Option<ImeMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub master_switch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_modes: Option<BTreeMap<String, ImeMode>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_profile_bindings"
+    )]
+    pub bindings: Option<BTreeMap<Trigger, Action>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_notifications: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded_apps: Option<Vec<String>>,
+}
+
+// 可绑定到快捷键的动作
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Action {
+    ToggleMasterSwitch,    // 切换总开关
+    SetMode(ImeMode),      // 切换到指定模式
+    CycleMode,             // 在中英文模式之间轮换
+    TogglePause,           // 暂停/恢复强制（不修改总开关，重启后失效）
+    OverrideCurrentWindow, // 当前窗口临时不强制，直到切换到其他窗口
+    ReloadConfig,          // 重新加载配置
+    OpenConfigDir,         // 打开配置目录
+    ExcludeCurrentApp,     // 将当前程序加入排除列表
+    ToggleNotifications,   // 切换通知显示
+    SetMasterSwitch(bool), // 开启或关闭总开关
+    Notify(String),        // 显示通知（不受 show_notifications 影响）
+    Macro(String),         // 执行 macros 中定义的宏
+    SwitchProfile(String), // 切换到 profiles 中的配置方案
+    ClearProfile,          // 取消配置方案，恢复顶层的设置
+}
+
+// 宏中的一个步骤：等待 delay_ms 后，若满足条件则执行动作
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct MacroStep {
+    pub action: Action,
+    #[serde(default)]
+    pub delay_ms: u32, // 执行前等待的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<MacroCondition>, // 不满足时跳过该步骤
+}
+
+// 宏步骤的执行条件，在等待结束、执行该步骤时判断
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum MacroCondition {
+    Mode(ImeMode),      // 当前输入法模式
+    MasterSwitch(bool), // 总开关状态
+    App(String),        // 前台程序名（不区分大小写）
+}
+
+impl Action {
+    pub fn description(&self) -> String {
+        match self {
+            Action::ToggleMasterSwitch => "总开关切换".to_string(),
+            Action::SetMode(ImeMode::ChineseOnly) => "切换到中文模式".to_string(),
+            Action::SetMode(ImeMode::EnglishOnly) => "切换到英文模式".to_string(),
+            Action::CycleMode => "中英文模式切换".to_string(),
+            Action::TogglePause => "暂停/恢复强制".to_string(),
+            Action::OverrideCurrentWindow => "当前窗口临时不强制".to_string(),
+            Action::ReloadConfig => "重新加载配置".to_string(),
+            Action::OpenConfigDir => "打开配置目录".to_string(),
+            Action::ExcludeCurrentApp => "排除当前程序".to_string(),
+            Action::ToggleNotifications => "通知显示切换".to_string(),
+            Action::SetMasterSwitch(true) => "开启总开关".to_string(),
+            Action::SetMasterSwitch(false) => "关闭总开关".to_string(),
+            Action::Notify(text) => format!("显示通知: {}", text),
+            Action::Macro(name) => format!("宏: {}", name),
+            Action::SwitchProfile(name) => format!("切换配置方案: {}", name),
+            Action::ClearProfile => "取消配置方案".to_string(),
+        }
+    }
+}
+
+fn default_bindings() -> BTreeMap<Trigger, Action> {
+    BTreeMap::from([
+        (
+            Trigger::Hotkey(Hotkey::new(MOD_ALT, 0x4D)), // Alt+M
+            Action::ToggleMasterSwitch,
+        ),
+        (Trigger::Hotkey(Hotkey::new(0, 0x14)), Action::CycleMode), // CapsLock
+    ])
+}
+
+// 解析 bindings，拒绝前缀有歧义的按键序列
+fn deserialize_bindings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<Trigger, Action>, D::Error> {
+    let bindings = BTreeMap::<Trigger, Action>::deserialize(deserializer)?;
+    check_chord_prefixes(bindings.keys()).map_err(de::Error::custom)?;
+    Ok(bindings)
+}
+
+fn deserialize_profile_bindings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<BTreeMap<Trigger, Action>>, D::Error> {
+    deserialize_bindings(deserializer).map(Some)
+}
+
+// 按出现顺序读取配置文件中的 bindings 原始条目，保留重复的键（反序列化为 BTreeMap 时后者会覆盖前者）
+#[derive(Deserialize)]
+struct RawBindings {
+    #[serde(default, deserialize_with = "deserialize_entries")]
+    bindings: Vec<(Trigger, Action)>,
+}
+
+fn deserialize_entries<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(Trigger, Action)>, D::Error> {
+    struct EntriesVisitor;
+
+    impl<'de> de::Visitor<'de> for EntriesVisitor {
+        type Value = Vec<(Trigger, Action)>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("热键到动作的映射")
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(EntriesVisitor)
+}
+
+// 检查重复的绑定：同一热键的不同写法（如 "Ctrl+M" 与 "control+m"），
+// 以及会被同一组按键同时触发的热键（如 "Ctrl+K" 与 "LCtrl+K"）
+fn find_duplicate_bindings(entries: &[(Trigger, Action)]) -> Vec<String> {
+    let mut duplicates = Vec::new();
+    for (i, (trigger, action)) in entries.iter().enumerate() {
+        for (other, other_action) in &entries[i + 1..] {
+            if trigger == other {
+                duplicates.push(format!(
+                    "{} 重复绑定了 {} 和 {}，仅后者生效",
+                    trigger,
+                    action.description(),
+                    other_action.description()
+                ));
+            } else if let (Trigger::Hotkey(a), Trigger::Hotkey(b)) = (trigger, other) {
+                if a.overlaps(b) {
+                    duplicates.push(format!(
+                        "{} ({}) 与 {} ({}) 会被同时触发",
+                        a,
+                        action.description(),
+                        b,
+                        other_action.description()
+                    ));
+                }
+            }
+        }
+    }
+    duplicates
+}
+
+fn default_tap_timeout_ms() -> u32 {
+    200
+}
+
+fn default_double_tap_interval_ms() -> u32 {
+    300
+}
+
+fn default_chord_timeout_ms() -> u32 {
+    1500
+}
+
+fn default_capslock_long_press_ms() -> u32 {
+    500
+}
+
+fn default_suspend_in_fullscreen() -> bool {
+    true
+}
+
+fn default_backup_count() -> usize {
+    5
+}
+
 // Global state
 lazy_static! {
-    pub static ref CONFIG: RwLock<Config> = RwLock::new(Config::load());
+    pub static ref CONFIG: RwLock<Config> = RwLock::new(load_or_default());
 
-    static ref CONFIG_PATH: PathBuf = {
-        let mut path = std::env::current_exe().unwrap();
-        path.pop(); // 去掉 exe 文件名
-        path.push("config.json");
-        path
-    };
+    // 最近一次加载配置文件失败的原因，修正并重新加载成功后清除
+    static ref CONFIG_LOAD_ERROR: Mutex<Option<ConfigError>> = Mutex::new(None);
+
+    // 最近一次读取或写入的配置文件内容，用于忽略自身保存引起的文件变化
+    static ref LAST_CONTENT: Mutex<Option<String>> = Mutex::new(None);
+
+    // 配置文件位置，启动时确定一次
+    static ref CONFIG_PATH: PathBuf = config_file_path().unwrap_or_else(|e| exit_with_error(&e));
+}
+
+// 无法确定配置文件位置时提示并退出，不能改用用户没有指定的配置文件
+fn exit_with_error(message: &str) -> ! {
+    error!("{}", message);
+    let text: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
+    let caption: Vec<u16> = "输入法控制"
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe {
+        MessageBoxW(
+            None,
+            PCWSTR(text.as_ptr()),
+            PCWSTR(caption.as_ptr()),
+            MB_OK | MB_ICONERROR,
+        );
+    }
+    std::process::exit(2);
+}
+
+// 加载配置文件失败的原因
+#[derive(Clone, Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub field: Option<String>, // 出错的字段，如 "oscillation_guard.window_secs"
+    pub message: String,
+}
+
+impl ConfigError {
+    fn io(path: PathBuf, e: std::io::Error) -> Self {
+        Self {
+            path,
+            line: None,
+            column: None,
+            field: None,
+            message: format!("无法读取: {}", e),
+        }
+    }
+
+    fn format(path: &Path, e: FormatError) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            line: e.line,
+            column: e.column,
+            field: e.field,
+            message: e.message,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " 第 {} 行第 {} 列", line, column)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, " 字段 {}", field)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+// 启动时加载配置，失败时在内存中使用默认配置（不写入文件）
+fn load_or_default() -> Config {
+    match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("配置文件加载失败，使用默认配置（不会覆盖配置文件）: {}", e);
+            *CONFIG_LOAD_ERROR.lock().unwrap() = Some(e);
+            Config::default()
+        }
+    }
+}
+
+// 最近一次加载配置文件失败的原因
+pub fn config_load_error() -> Option<ConfigError> {
+    CONFIG_LOAD_ERROR.lock().unwrap().clone()
+}
+
+// 配置文件有误时通过托盘通知提示
+pub fn notify_config_error(hwnd: HWND) {
+    if let Some(e) = config_load_error() {
+        show_balloon_tip(
+            hwnd,
+            "配置文件有误",
+            &format!("{}\n修正后请重新加载配置，在此之前不会覆盖配置文件", e),
+        );
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
+    #[serde(default)]
+    pub version: u64, // 配置文件格式的版本，加载时自动迁移到最新版本
     pub autostart: bool,
     pub excluded_apps: Vec<String>,
-    pub hotkey_toggle: Option<String>, // 切换总开关快捷键 (如: "Alt+M")
-    pub hotkey_switch_mode: Option<String>, // 切换中英文模式快捷键 (如: "Alt+S")
-    pub ime_mode: ImeMode,             // 输入法模式
-    pub master_switch: bool,           // 总开关
-    pub show_notifications: bool,      // 是否显示通知
+    #[serde(
+        default = "default_bindings",
+        deserialize_with = "deserialize_bindings"
+    )]
+    pub bindings: BTreeMap<Trigger, Action>, // 快捷键 -> 动作 (如: "Alt+M": "ToggleMasterSwitch")
+    // 旧版保存在配置中的输入法模式和总开关，仅作为状态文件的初始值，之后从配置文件中删除
+    #[serde(rename = "ime_mode", default, skip_serializing_if = "Option::is_none")]
+    pub legacy_ime_mode: Option<ImeMode>,
+    #[serde(
+        rename = "master_switch",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub legacy_master_switch: Option<bool>,
+    pub show_notifications: bool, // 是否显示通知
+    #[serde(default = "default_tap_timeout_ms")]
+    pub tap_timeout_ms: u32, // 轻按 (Tap) 的判定时间
+    #[serde(default = "default_double_tap_interval_ms")]
+    pub double_tap_interval_ms: u32, // 双击 (DoubleTap) 两次轻按的最大间隔
+    #[serde(default = "default_chord_timeout_ms")]
+    pub chord_timeout_ms: u32, // 按键序列 (如 "Ctrl+K, E") 等待第二段按键的时间
+    #[serde(default)]
+    pub capslock_mode: CapsLockMode, // 绑定了 CapsLock 时大小写锁定的切换方式
+    #[serde(default = "default_capslock_long_press_ms")]
+    pub capslock_long_press_ms: u32, // LongPressToggles 模式下长按的判定时间
+    #[serde(default)]
+    pub enforce_strategy: EnforceStrategy, // 强制输入法模式的策略
+    #[serde(default)]
+    pub macros: BTreeMap<String, Vec<MacroStep>>, // 宏名称 -> 按顺序执行的步骤
+    #[serde(default = "default_ime_toggle_keys")]
+    pub ime_toggle_keys: HashMap<String, String>, // 各输入法的中英切换键 (键为 KLID 如 "E0210804"，或 "default")
+    #[serde(default)]
+    pub oscillation_guard: OscillationGuard, // 拉锯检测与自动退避
+    #[serde(default)]
+    pub pinyin_settings: PinyinSettings, // 微软拼音设置
+    #[serde(default)]
+    pub suspend_apps: Vec<String>, // 游戏模式：这些程序在前台时暂停热键与强制
+    #[serde(default = "default_suspend_in_fullscreen")]
+    pub suspend_in_fullscreen: bool, // 独占全屏程序在前台时同样暂停
+    #[serde(default = "default_backup_count")]
+    pub backup_count: usize, // 保存配置时保留的备份数量
+    #[serde(default)]
+    pub app_modes: BTreeMap<String, ImeMode>, // 程序名 -> 该程序固定使用的输入法模式（不区分大小写）
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>, // 配置方案名称 -> 覆盖的设置
+    #[serde(skip)]
+    pub active_profile: Option<String>, // 当前生效的配置方案，保存在运行状态中
+    #[serde(skip)]
+    pub duplicate_bindings: Vec<String>, // 加载时发现的重复绑定，不保存
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CURRENT_VERSION,
             autostart: false,
             excluded_apps: vec!["ime-controller.exe".to_string()],
-            hotkey_toggle: Some("Alt+M".to_string()), // Alt+M (总开关)
-            hotkey_switch_mode: Some("CAPSLOCK".to_string()), // CAPSLOCK (切换中英文模式)
-            ime_mode: ImeMode::default(),
-            master_switch: true,       // 默认总开关为开启
+            bindings: default_bindings(), // Alt+M (总开关), CAPSLOCK (切换中英文模式)
+            macros: BTreeMap::new(),
+            tap_timeout_ms: default_tap_timeout_ms(),
+            double_tap_interval_ms: default_double_tap_interval_ms(),
+            chord_timeout_ms: default_chord_timeout_ms(),
+            capslock_mode: CapsLockMode::default(),
+            capslock_long_press_ms: default_capslock_long_press_ms(),
+            legacy_ime_mode: None,
+            legacy_master_switch: None,
             show_notifications: false, // 默认不显示通知，避免打扰
+            enforce_strategy: EnforceStrategy::default(),
+            ime_toggle_keys: default_ime_toggle_keys(),
+            oscillation_guard: OscillationGuard::default(),
+            pinyin_settings: PinyinSettings::default(),
+            suspend_apps: Vec::new(),
+            suspend_in_fullscreen: default_suspend_in_fullscreen(),
+            backup_count: default_backup_count(),
+            app_modes: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            duplicate_bindings: Vec::new(),
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Self {
+    // 加载配置文件。文件不存在时写入并使用默认配置；解析失败时返回错误，不修改文件
+    pub fn load() -> Result<Self, ConfigError> {
         let config_path = Self::get_config_path();
-        if let Ok(content) = fs::read_to_string(config_path) {
-            info!("加载配置文件: {:?}", content);
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            info!("配置文件不存在，使用默认配置");
-            let config = Config::default();
+        let content = match fs::read_to_string(&config_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!("配置文件不存在，使用默认配置");
+                let config = Config::default();
+                if let Err(e) = config.save() {
+                    error!("保存默认配置文件失败: {}", e);
+                }
+                return Ok(config);
+            }
+            Err(e) => return Err(ConfigError::io(config_path, e)),
+        };
+
+        info!("加载配置文件: {:?}", content);
+        *LAST_CONTENT.lock().unwrap() = Some(content.clone());
+        let (mut config, migrated) = Self::parse(&content)?;
+        if let Ok(raw) = ConfigFormat::from_path(&config_path).deserialize::<RawBindings>(&content)
+        {
+            config.duplicate_bindings = find_duplicate_bindings(&raw.bindings);
+            for duplicate in &config.duplicate_bindings {
+                warn!("重复的绑定: {}", duplicate);
+            }
+        }
+        for problem in config.check_macros() {
+            warn!("宏配置有误: {}", problem);
+        }
+        let mut dropped_legacy = false;
+        if config.legacy_master_switch.is_some() || config.legacy_ime_mode.is_some() {
+            // 状态文件写入成功后才删除，以免丢失旧版的运行状态
+            if seed_from_legacy_config(config.legacy_master_switch, config.legacy_ime_mode.clone())
+            {
+                info!(
+                    "配置文件中的 master_switch / ime_mode 已不再使用，运行状态保存在 {}，从配置文件中删除",
+                    STATE_FILE_NAME
+                );
+                config.legacy_master_switch = None;
+                config.legacy_ime_mode = None;
+                dropped_legacy = true;
+            }
+        }
+        config.restore_active_profile();
+        if migrated || dropped_legacy {
             if let Err(e) = config.save() {
-                error!("保存默认配置文件失败: {}", e);
+                error!("保存更新后的配置文件失败: {}", e);
             }
-            config
         }
+        Ok(config)
     }
 
     pub fn save(&self) -> std::io::Result<()> {
+        // 配置文件有误时不覆盖，以免丢失用户的设置
+        if let Some(e) = config_load_error() {
+            warn!("配置文件有误，暂不保存: {}", e);
+            return Err(std::io::Error::other(format!("配置文件有误: {}", e)));
+        }
         let config_path = Self::get_config_path();
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(config_path, json)
+        let format = ConfigFormat::from_path(&config_path);
+        let content = match fs::read_to_string(&config_path) {
+            Ok(old) => match self.patch_content(format, &old) {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("无法在原文件上修改（{}），重写整个配置文件", e);
+                    format.serialize(self).map_err(std::io::Error::other)?
+                }
+            },
+            Err(_) => format.serialize(self).map_err(std::io::Error::other)?,
+        };
+        save_with_backup(&config_path, &content, self.backup_count)?;
+        *LAST_CONTENT.lock().unwrap() = Some(content);
+        Ok(())
+    }
+
+    // 在原文件上只修改有变化的字段，保留注释、顺序和未知字段。
+    // 原文件需要迁移时返回错误，迁移后的配置整体重写（原文件已备份）
+    fn patch_content(&self, format: ConfigFormat, old: &str) -> Result<String, String> {
+        let mut value = format.parse_value(old).map_err(|e| e.message)?;
+        if migrate(&mut value)?.is_some() {
+            return Err("原文件的版本较旧".to_string());
+        }
+        let previous: Config = ConfigFormat::deserialize_value(value).map_err(|e| e.message)?;
+        patch(format, old, &previous, self)
     }
 
-    fn get_config_path() -> PathBuf {
-        let mut path = PathBuf::from(std::env::current_exe().unwrap().parent().unwrap());
-        info!("配置文件目录: {:?}", path);
-        path.push("config.json");
+    // 热键相关的设置是否相同，相同时重新加载配置无需重新注册热键
+    fn same_hotkey_settings(&self, other: &Config) -> bool {
+        self.bindings() == other.bindings()
+            && self.capslock_mode == other.capslock_mode
+            && self.tap_timeout_ms == other.tap_timeout_ms
+            && self.double_tap_interval_ms == other.double_tap_interval_ms
+            && self.chord_timeout_ms == other.chord_timeout_ms
+            && self.capslock_long_press_ms == other.capslock_long_press_ms
+    }
+
+    // 解析配置，旧版本的配置先备份再逐版本迁移，返回配置及是否发生了迁移
+    fn parse(content: &str) -> Result<(Config, bool), ConfigError> {
+        let path = Self::get_config_path();
+        let format = ConfigFormat::from_path(&path);
+        let mut value = format
+            .parse_value(content)
+            .map_err(|e| ConfigError::format(&path, e))?;
+        let report = migrate(&mut value).map_err(|message| ConfigError {
+            path: path.clone(),
+            line: None,
+            column: None,
+            field: Some("version".to_string()),
+            message,
+        })?;
+        // 未迁移时直接解析原文，以便错误信息带有行号和列号
+        let config = if report.is_none() {
+            format.deserialize(content)
+        } else {
+            ConfigFormat::deserialize_value(value)
+        }
+        .map_err(|e| ConfigError::format(&path, e))?;
+
+        let Some(report) = report else {
+            return Ok((config, false));
+        };
+        let backup_path = with_suffix(&path, &format!(".v{}.bak", report.from_version));
+        if let Err(e) = fs::write(&backup_path, content) {
+            // 没有备份时不覆盖原文件
+            error!("备份配置文件失败，本次不保存迁移结果: {}", e);
+            return Ok((config, false));
+        }
+        info!(
+            "配置文件从版本 {} 迁移到版本 {}，原文件已备份到 {:?}",
+            report.from_version, CURRENT_VERSION, backup_path
+        );
+        for change in &report.changes {
+            info!("配置迁移: {}", change);
+        }
+        Ok((config, true))
+    }
+
+    // 检查宏和配置方案：绑定引用的宏和配置方案必须存在，宏中不能再调用宏
+    fn check_macros(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let profile_bindings = self.profiles.values().filter_map(|p| p.bindings.as_ref());
+        for (trigger, action) in std::iter::once(&self.bindings)
+            .chain(profile_bindings)
+            .flatten()
+        {
+            match action {
+                Action::Macro(name) if !self.macros.contains_key(name) => {
+                    problems.push(format!("{} 绑定的宏 \"{}\" 不存在", trigger, name));
+                }
+                Action::SwitchProfile(name) if !self.profiles.contains_key(name) => {
+                    problems.push(format!("{} 绑定的配置方案 \"{}\" 不存在", trigger, name));
+                }
+                _ => {}
+            }
+        }
+        for (name, steps) in &self.macros {
+            for step in steps {
+                if let Action::Macro(inner) = &step.action {
+                    problems.push(format!(
+                        "宏 \"{}\" 中调用了宏 \"{}\"，宏不能嵌套，该步骤将被跳过",
+                        name, inner
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    // 程序名是否在排除列表中（不区分大小写）
+    pub fn is_excluded(&self, app: &str) -> bool {
+        self.excluded_apps()
+            .iter()
+            .any(|excluded| excluded.eq_ignore_ascii_case(app))
+    }
+
+    // 程序固定使用的输入法模式（不区分大小写），没有时使用当前模式
+    pub fn app_mode(&self, app: &str) -> Option<ImeMode> {
+        self.profile()
+            .and_then(|profile| profile.app_modes.as_ref())
+            .unwrap_or(&self.app_modes)
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(app))
+            .map(|(_, mode)| mode.clone())
+    }
+
+    // 当前生效的配置方案
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiles.get(self.active_profile.as_ref()?)
+    }
+
+    // 以下设置可被配置方案覆盖，读取和修改时使用当前生效的值
+    pub fn bindings(&self) -> &BTreeMap<Trigger, Action> {
+        self.profile()
+            .and_then(|profile| profile.bindings.as_ref())
+            .unwrap_or(&self.bindings)
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut BTreeMap<Trigger, Action> {
+        let Config {
+            profiles,
+            active_profile,
+            bindings,
+            ..
+        } = self;
+        active_profile
+            .as_ref()
+            .and_then(|name| profiles.get_mut(name))
+            .and_then(|profile| profile.bindings.as_mut())
+            .unwrap_or(bindings)
+    }
+
+    pub fn show_notifications(&self) -> bool {
+        self.profile()
+            .and_then(|profile| profile.show_notifications)
+            .unwrap_or(self.show_notifications)
+    }
+
+    pub fn set_show_notifications(&mut self, on: bool) {
+        let active = self.active_profile.as_ref();
+        match active
+            .and_then(|name| self.profiles.get_mut(name))
+            .and_then(|profile| profile.show_notifications.as_mut())
+        {
+            Some(show_notifications) => *show_notifications = on,
+            None => self.show_notifications = on,
+        }
+    }
+
+    pub fn excluded_apps(&self) -> &[String] {
+        self.profile()
+            .and_then(|profile| profile.excluded_apps.as_ref())
+            .unwrap_or(&self.excluded_apps)
+    }
+
+    pub fn excluded_apps_mut(&mut self) -> &mut Vec<String> {
+        let Config {
+            profiles,
+            active_profile,
+            excluded_apps,
+            ..
+        } = self;
+        active_profile
+            .as_ref()
+            .and_then(|name| profiles.get_mut(name))
+            .and_then(|profile| profile.excluded_apps.as_mut())
+            .unwrap_or(excluded_apps)
+    }
+
+    // 恢复运行状态中保存的配置方案，配置中已没有该方案时使用顶层的设置
+    fn restore_active_profile(&mut self) {
+        let name = STATE.read().unwrap().active_profile.clone();
+        self.restore_profile(name);
+    }
+
+    fn restore_profile(&mut self, name: Option<String>) {
+        match name {
+            Some(name) if !self.profiles.contains_key(&name) => {
+                warn!("配置方案 \"{}\" 已不存在，使用默认设置", name);
+                self.active_profile = None;
+            }
+            name => self.active_profile = name,
+        }
+    }
+
+    // 程序名是否在游戏模式列表中（不区分大小写）
+    pub fn is_suspend_app(&self, app: &str) -> bool {
+        self.suspend_apps
+            .iter()
+            .any(|suspended| suspended.eq_ignore_ascii_case(app))
+    }
+
+    pub fn config_dir() -> PathBuf {
+        let mut path = Self::get_config_path();
+        path.pop();
         path
     }
+
+    pub fn get_config_path() -> PathBuf {
+        CONFIG_PATH.clone()
+    }
 }
 
 /// 切换总开关
 pub fn toggle_master_switch(hwnd: HWND) {
-    let mut config = CONFIG.write().unwrap();
-    config.master_switch = !config.master_switch;
-    let new_state = config.master_switch;
-    config.save().ok();
-    drop(config);
+    let new_state = update_state(|state| state.master_switch = !state.master_switch).master_switch;
 
     let _ = update_tray_icon(hwnd, new_state);
 
-    if CONFIG.read().unwrap().show_notifications {
+    if CONFIG.read().unwrap().show_notifications() {
         show_balloon_tip(
             hwnd,
             "状态更改",
@@ -146,20 +811,18 @@ pub fn toggle_autostart(hwnd: HWND) {
 /// 切换通知显示
 pub fn toggle_notifications(hwnd: HWND) {
     let mut config = CONFIG.write().unwrap();
-    config.show_notifications = !config.show_notifications;
+    let show_notifications = !config.show_notifications();
+    config.set_show_notifications(show_notifications);
     config.save().ok();
 
-    if config.show_notifications {
+    if show_notifications {
         show_balloon_tip(hwnd, "设置", "已开启通知显示");
     }
 }
 
 /// 切换输入法模式
 pub fn switch_ime_mode(hwnd: HWND, mode: ImeMode) {
-    let mut config = CONFIG.write().unwrap();
-    config.ime_mode = mode.clone();
-    config.save().ok();
-    drop(config);
+    update_state(|state| state.ime_mode = mode.clone());
 
     apply_ime_setting_to_current_window(hwnd, mode.clone());
 
@@ -173,11 +836,100 @@ pub fn switch_ime_mode(hwnd: HWND, mode: ImeMode) {
     );
 }
 
+/// 切换配置方案，None 表示取消配置方案、恢复顶层的设置。配置方案不存在时提示并返回错误
+pub fn switch_profile(hwnd: HWND, name: Option<&str>) -> Result<(), String> {
+    let mut config = CONFIG.write().unwrap();
+    let profile = match name {
+        Some(name) => match config.profiles.get(name) {
+            Some(profile) => Some(profile.clone()),
+            None => {
+                drop(config);
+                let message = format!("配置方案 \"{}\" 不存在", name);
+                warn!("{}", message);
+                show_balloon_tip(hwnd, "配置方案", &message);
+                return Err(message);
+            }
+        },
+        None => None,
+    };
+    config.active_profile = name.map(str::to_string);
+    let show_notifications = config.show_notifications();
+    drop(config);
+
+    let state = update_state(|state| {
+        state.active_profile = name.map(str::to_string);
+        if let Some(profile) = &profile {
+            if let Some(mode) = &profile.ime_mode {
+                state.ime_mode = mode.clone();
+            }
+            if let Some(on) = profile.master_switch {
+                state.master_switch = on;
+            }
+        }
+    });
+    info!("切换配置方案: {}", name.unwrap_or("无"));
+
+    refresh_hotkeys(hwnd);
+    let _ = update_tray_icon(hwnd, state.master_switch);
+    if state.master_switch {
+        apply_mode_to_foreground_window(hwnd, state.ime_mode);
+    }
+    if show_notifications {
+        show_balloon_tip(
+            hwnd,
+            "配置方案",
+            &match name {
+                Some(name) => format!("已切换到配置方案: {}", name),
+                None => "已取消配置方案，使用默认设置".to_string(),
+            },
+        );
+    }
+    Ok(())
+}
+
+// 按当前生效的 app_modes / excluded_apps 对前台窗口重新应用输入法模式
+fn apply_mode_to_foreground_window(hwnd: HWND, mode: ImeMode) {
+    let foreground = unsafe { GetForegroundWindow() };
+    let app = process_name_of_window(foreground);
+    let config = CONFIG.read().unwrap();
+    if app.as_deref().is_some_and(|app| config.is_excluded(app)) {
+        return;
+    }
+    let mode = app
+        .as_deref()
+        .and_then(|app| config.app_mode(app))
+        .unwrap_or(mode);
+    drop(config);
+    apply_ime_setting_to_current_window(hwnd, mode);
+}
+
 /// 应用输入法设置到当前窗口
 pub fn apply_ime_setting_to_current_window(hwnd: HWND, config: ImeMode) {
     unsafe {
-        enforce_global_ime_mode(hwnd, &config);
+        enforce_ime_mode(hwnd, &config);
+    }
+}
+
+/// 将前台窗口所属程序加入排除列表
+pub fn exclude_current_app(hwnd: HWND) {
+    let foreground = unsafe { GetForegroundWindow() };
+    let Some(app) = process_name_of_window(foreground) else {
+        warn!("无法获取当前程序名称");
+        return;
+    };
+
+    let mut config = CONFIG.write().unwrap();
+    if config.is_excluded(&app) {
+        drop(config);
+        show_balloon_tip(hwnd, "排除列表", &format!("{} 已在排除列表中", app));
+        return;
     }
+    config.excluded_apps_mut().push(app.clone());
+    config.save().ok();
+    drop(config);
+
+    info!("已将 {} 加入排除列表", app);
+    show_balloon_tip(hwnd, "排除列表", &format!("已将 {} 加入排除列表", app));
 }
 
 /// 打开配置目录
@@ -188,11 +940,194 @@ pub fn open_config_directory() {
     }
 }
 
-/// 重新加载配置并刷新热键
+/// 用最近的备份替换配置文件，返回所用的备份（配置文件有误时同样可以恢复）。
+/// 连续恢复时逐个回到更早的备份
+pub fn restore_previous_config() -> std::io::Result<PathBuf> {
+    let last_restored = STATE.read().unwrap().restored_backup.clone();
+    let backup = restore_previous(&Config::get_config_path(), last_restored.as_deref())?;
+    info!("已从备份恢复配置文件: {:?}", backup);
+    update_state(|state| state.restored_backup = Some(backup.clone()));
+    Ok(backup)
+}
+
+/// 恢复上一个配置并重新加载
+pub fn restore_previous_config_and_reload(hwnd: HWND) {
+    match restore_previous_config() {
+        Ok(backup) => {
+            reload_config_and_hotkeys(hwnd);
+            if config_load_error().is_none() {
+                let name = backup.file_name().unwrap_or_default().to_string_lossy();
+                show_balloon_tip(hwnd, "恢复配置", &format!("已从备份 {} 恢复配置", name));
+            }
+        }
+        Err(e) => {
+            error!("恢复配置失败: {}", e);
+            show_balloon_tip(hwnd, "恢复配置", &format!("恢复失败: {}", e));
+        }
+    }
+}
+
+// 加载配置文件并替换当前配置，返回替换前的配置；配置文件有误时保留当前配置并提示
+fn load_and_replace(hwnd: HWND) -> Option<Config> {
+    // 清除上次的错误，使迁移后的配置可以写回
+    *CONFIG_LOAD_ERROR.lock().unwrap() = None;
+    match Config::load() {
+        Ok(config) => Some(std::mem::replace(&mut *CONFIG.write().unwrap(), config)),
+        Err(e) => {
+            error!("加载配置失败，保留当前配置: {}", e);
+            *CONFIG_LOAD_ERROR.lock().unwrap() = Some(e);
+            notify_config_error(hwnd);
+            None
+        }
+    }
+}
+
+/// 重新加载配置并刷新热键，配置文件有误时保留当前配置
 pub fn reload_config_and_hotkeys(hwnd: HWND) {
-    let mut config = CONFIG.write().unwrap();
-    *config = Config::load();
-    drop(config);
+    if load_and_replace(hwnd).is_none() {
+        return;
+    }
+    let pinyin_settings = CONFIG.read().unwrap().pinyin_settings.clone();
+    apply_pinyin_settings(&pinyin_settings);
     register_all_hotkeys(hwnd);
     info!("配置重新加载完成");
 }
+
+/// 配置文件在外部被修改后自动重新加载，只重新注册有变化的热键
+pub fn reload_config_if_changed(hwnd: HWND) {
+    let content = match fs::read_to_string(Config::get_config_path()) {
+        Ok(content) => content,
+        // 编辑器保存时可能先删除原文件，稍后会收到新文件的事件
+        Err(e) => {
+            debug!("读取配置文件失败，忽略本次变化: {}", e);
+            return;
+        }
+    };
+    if LAST_CONTENT.lock().unwrap().as_deref() == Some(content.as_str()) {
+        debug!("配置文件内容未变化，忽略");
+        return;
+    }
+
+    info!("检测到配置文件变化，自动重新加载");
+    let Some(old) = load_and_replace(hwnd) else {
+        return;
+    };
+    let config = CONFIG.read().unwrap();
+    let pinyin_changed = config.pinyin_settings != old.pinyin_settings;
+    let hotkeys_changed = !config.same_hotkey_settings(&old);
+    let pinyin_settings = config.pinyin_settings.clone();
+    let show_notifications = config.show_notifications();
+    drop(config);
+
+    if pinyin_changed {
+        apply_pinyin_settings(&pinyin_settings);
+    }
+    if hotkeys_changed {
+        refresh_hotkeys(hwnd);
+    }
+    info!("配置自动重新加载完成");
+    if show_notifications {
+        show_balloon_tip(hwnd, "配置已更新", "检测到配置文件变化，已自动重新加载");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_bindings_use_defaults() {
+        let config: Config = serde_json::from_str(
+            r#"{"version": 1, "autostart": false, "excluded_apps": [], "show_notifications": true}"#,
+        )
+        .unwrap();
+        assert_eq!(config.bindings, default_bindings());
+
+        let config: Config = serde_json::from_str(
+            r#"{"version": 1, "autostart": false, "excluded_apps": [], "show_notifications": true, "bindings": {}}"#,
+        )
+        .unwrap();
+        assert!(config.bindings.is_empty());
+    }
+
+    #[test]
+    fn legacy_state_keys_are_dropped_on_save() {
+        let content = "{\n  \"version\": 1,\n  \"autostart\": false,\n  \"excluded_apps\": [],\n  \"master_switch\": false, // 旧版的总开关\n  \"ime_mode\": \"EnglishOnly\",\n  \"show_notifications\": true\n}\n";
+        let mut config: Config =
+            serde_json::from_str(&crate::utils::jsonc::strip(content)).unwrap();
+        assert_eq!(config.legacy_master_switch, Some(false));
+        assert_eq!(config.legacy_ime_mode, Some(ImeMode::EnglishOnly));
+
+        config.legacy_master_switch = None;
+        config.legacy_ime_mode = None;
+        assert_eq!(
+            config.patch_content(ConfigFormat::Json, content).unwrap(),
+            "{\n  \"version\": 1,\n  \"autostart\": false,\n  \"excluded_apps\": [],\n  \"show_notifications\": true\n}\n"
+        );
+    }
+
+    fn work_profile() -> Config {
+        let mut config = Config {
+            excluded_apps: vec!["game.exe".to_string()],
+            show_notifications: false,
+            ..Config::default()
+        };
+        config
+            .app_modes
+            .insert("Code.exe".to_string(), ImeMode::EnglishOnly);
+        config.profiles.insert(
+            "工作".to_string(),
+            Profile {
+                bindings: Some(BTreeMap::from([(
+                    "Ctrl+1".parse().unwrap(),
+                    Action::CycleMode,
+                )])),
+                show_notifications: Some(true),
+                ..Profile::default()
+            },
+        );
+        config.restore_profile(Some("工作".to_string()));
+        config
+    }
+
+    #[test]
+    fn profile_overrides_some_settings_and_inherits_the_rest() {
+        let config = work_profile();
+        assert_eq!(config.active_profile.as_deref(), Some("工作"));
+        assert_eq!(config.bindings().len(), 1);
+        assert!(config.show_notifications());
+        // 方案中没有写出的设置沿用顶层的值
+        assert!(config.is_excluded("GAME.EXE"));
+        assert_eq!(config.app_mode("code.exe"), Some(ImeMode::EnglishOnly));
+    }
+
+    #[test]
+    fn mutations_go_to_the_profile_only_when_it_defines_the_field() {
+        let mut config = work_profile();
+        config
+            .bindings_mut()
+            .insert("Ctrl+2".parse().unwrap(), Action::ToggleMasterSwitch);
+        config.set_show_notifications(false);
+        config.excluded_apps_mut().push("video.exe".to_string());
+
+        let profile = &config.profiles["工作"];
+        assert_eq!(profile.bindings.as_ref().unwrap().len(), 2);
+        assert_eq!(profile.show_notifications, Some(false));
+        assert_eq!(profile.excluded_apps, None);
+        assert_eq!(config.bindings, default_bindings());
+        assert!(!config.show_notifications);
+        assert_eq!(config.excluded_apps, vec!["game.exe", "video.exe"]);
+    }
+
+    #[test]
+    fn missing_profile_falls_back_to_top_level_settings() {
+        let mut config = work_profile();
+        config.restore_profile(Some("已删除".to_string()));
+        assert_eq!(config.active_profile, None);
+        assert_eq!(config.bindings(), &default_bindings());
+        assert!(!config.show_notifications());
+
+        config.restore_profile(None);
+        assert_eq!(config.active_profile, None);
+    }
+}
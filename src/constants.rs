@@ -13,7 +13,21 @@ pub const IDM_TOGGLE_NOTIFICATIONS: u32 = 1008;
 pub const IDM_CONFIGURE_HOTKEYS: u32 = 1009;
 pub const IDM_OPEN_CONFIG_DIR: u32 = 1010;
 pub const IDM_RELOAD_CONFIG: u32 = 1011;
+pub const IDM_RETRY_HOTKEYS: u32 = 1012;
+pub const IDM_RESTORE_CONFIG: u32 = 1013;
+pub const IDM_PROFILE_NONE: u32 = 1014; // 配置方案子菜单中的"默认设置"
+pub const IDM_HOTKEY_ALTERNATIVE_BASE: u32 = 1100; // 热键冲突子菜单项，按 冲突序号 * 候选数 + 候选序号 分配
+pub const IDM_PROFILE_BASE: u32 = 1200; // 配置方案子菜单项，按 profiles 中的顺序分配
+pub const IDM_PROFILE_END: u32 = 1300; // 配置方案子菜单项的上限（不含）
 pub const NOTIFYICONMESSAGE: u32 = WM_USER + 100;
-pub const HOT_KEY_TOGGLE_ID: i32 = 1; // 切换总开关
-pub const HOT_KEY_SWITCH_MODE_ID: i32 = 2; // 切换中英文模式
+pub const WM_HOOK_ACTION: u32 = WM_USER + 101; // 键盘钩子触发的动作，wparam 为动作编号
+pub const WM_MOUSE_ACTION: u32 = WM_USER + 102; // 鼠标钩子触发的动作，wparam 为动作编号
+pub const WM_CONFIG_CHANGED: u32 = WM_USER + 103; // 配置文件在外部被修改
+pub const HOT_KEY_ID_BASE: i32 = 1; // 热键ID按 bindings 顺序从此值开始动态分配
+pub const HOT_KEY_PROBE_ID: i32 = 0xBFFF; // 探测替代热键是否可用时临时使用的热键ID
 pub const TIMER_ID_IME_CHECK: usize = 1; // 定时器ID，用于定时检测输入法状态
+pub const TIMER_ID_KEYSTROKE_VERIFY: usize = 2; // 定时器ID，模拟按键后等待输入法响应再验证
+pub const TIMER_ID_SUSPEND_CHECK: usize = 3; // 定时器ID，切换前台程序后延迟检测是否独占全屏
+pub const TIMER_ID_HOTKEY_RETRY: usize = 4; // 定时器ID，热键注册失败后按退避时间重试
+pub const TIMER_ID_MACRO: usize = 5; // 定时器ID，宏步骤的延时
+pub const INJECTED_INPUT_MARKER: usize = 0x494D_4543; // 本程序模拟按键的 dwExtraInfo 标记 ("IMEC")
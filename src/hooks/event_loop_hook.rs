@@ -1,11 +1,59 @@
-use crate::config::{Config, ImeMode, CONFIG};
+use crate::config::{Config, EnforceStrategy, ImeMode, CONFIG};
+use crate::constants;
+use crate::ime::keystroke::{
+    parse_key_sequence, ConversionState, KeystrokeEnforcer, KeystrokeStep,
+};
+use crate::ime::oscillation::OscillationDetector;
+use crate::hooks::suspend::{is_suspended, update_suspension};
+use crate::ime::win_input::{ImmConversionState, SendInputInjector};
+use crate::state::STATE;
+use crate::tray::main_window;
+use crate::tray::notifications::show_balloon_tip;
+use crate::utils::window_process::process_name_of_window;
+use lazy_static::lazy_static;
 use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
 use windows::core::PCWSTR;
 use windows::Win32::{
     Foundation::{HWND, LPARAM, WPARAM},
+    System::Threading::GetCurrentThreadId,
     UI::{Accessibility::*, Input::KeyboardAndMouse::*, WindowsAndMessaging::*},
 };
 
+// 等待验证的模拟按键强制
+struct PendingKeystroke {
+    target: usize,
+    klid: String,
+    enforcer: KeystrokeEnforcer,
+}
+
+lazy_static! {
+    static ref OSCILLATION: Mutex<OscillationDetector<usize>> =
+        Mutex::new(OscillationDetector::new());
+    static ref PENDING_KEYSTROKE: Mutex<Option<PendingKeystroke>> = Mutex::new(None);
+}
+
+// 运行时暂停强制（不写入配置）
+static PAUSED: AtomicBool = AtomicBool::new(false);
+// 临时不强制的窗口，切换到其他窗口后失效
+static OVERRIDE_WINDOW: AtomicUsize = AtomicUsize::new(0);
+
+// 暂停/恢复强制，返回切换后是否处于暂停状态
+pub fn toggle_pause() -> bool {
+    let paused = !PAUSED.fetch_xor(true, Ordering::Relaxed);
+    info!("强制输入法模式已{}", if paused { "暂停" } else { "恢复" });
+    paused
+}
+
+// 当前窗口临时不强制
+pub fn override_window(hwnd: HWND) {
+    info!("窗口临时不强制: {:?}", hwnd);
+    OVERRIDE_WINDOW.store(hwnd.0 as usize, Ordering::Relaxed);
+}
+
 pub unsafe extern "system" fn event_hook_callback(
     _h_win_event_hook: HWINEVENTHOOK,
     _event: u32,
@@ -15,24 +63,216 @@ pub unsafe extern "system" fn event_hook_callback(
     _id_event_thread: u32,
     _dwms_event_time: u32,
 ) {
-    let master_switch = CONFIG.read().unwrap().master_switch;
-    if !master_switch {
+    update_suspension(hwnd);
+    if is_suspended() {
         return;
     }
 
-    // Clone the config data we need
-    let ime_mode = CONFIG.read().unwrap().ime_mode.clone();
+    let master_switch = STATE.read().unwrap().master_switch;
+    if !master_switch || PAUSED.load(Ordering::Relaxed) {
+        return;
+    }
 
-    // 在新线程中执行，避免阻塞事件钩子
     let hwnd_raw = hwnd.0 as usize;
+    let overridden = OVERRIDE_WINDOW.load(Ordering::Relaxed);
+    if overridden == hwnd_raw {
+        return;
+    } else if overridden != 0 {
+        OVERRIDE_WINDOW.store(0, Ordering::Relaxed);
+    }
+
+    // Clone the state we need
+    let mut ime_mode = STATE.read().unwrap().ime_mode.clone();
+
+    // 在新线程中执行，避免阻塞事件钩子
     std::thread::spawn(move || {
         std::thread::sleep(std::time::Duration::from_millis(100)); // 等待窗口稳定
+        let hwnd = HWND(hwnd_raw as *mut std::ffi::c_void);
+        if let Some(app) = process_name_of_window(hwnd) {
+            let config = CONFIG.read().unwrap();
+            if config.is_excluded(&app) {
+                debug!("排除的程序，跳过强制: {}", app);
+                return;
+            }
+            // app_modes 中指定了模式的程序使用固定的模式
+            if let Some(mode) = config.app_mode(&app) {
+                debug!("{} 使用固定的输入法模式: {:?}", app, mode);
+                ime_mode = mode;
+            }
+        }
         unsafe {
-            enforce_global_ime_mode(HWND(hwnd_raw as *mut std::ffi::c_void), &ime_mode);
+            enforce_with_oscillation_guard(hwnd, &ime_mode);
         }
     });
 }
 
+// 强制前后进行拉锯检测，对反复被切回的窗口自动退避
+unsafe fn enforce_with_oscillation_guard(hwnd: HWND, ime_mode: &ImeMode) {
+    let guard = CONFIG.read().unwrap().oscillation_guard.clone();
+    if !guard.enabled {
+        enforce_ime_mode(hwnd, ime_mode);
+        return;
+    }
+
+    let key = hwnd.0 as usize;
+    if OSCILLATION
+        .lock()
+        .unwrap()
+        .is_backing_off(&key, Instant::now())
+    {
+        debug!("窗口处于退避期，跳过强制: {:?}", hwnd);
+        return;
+    }
+
+    // 已是目标模式则无需强制，也不计入拉锯
+    if window_in_mode(hwnd, ime_mode) == Some(true) {
+        return;
+    }
+
+    enforce_ime_mode(hwnd, ime_mode);
+
+    let backoff = OSCILLATION
+        .lock()
+        .unwrap()
+        .record_enforcement(key, Instant::now(), &guard);
+    let Some(backoff) = backoff else {
+        return;
+    };
+
+    let app = process_name_of_window(hwnd).unwrap_or_else(|| "未知程序".to_string());
+    warn!(
+        "检测到输入法被反复切回: {} ({} 秒内强制 {} 次)，暂停强制该窗口 {} 秒",
+        app, guard.window_secs, backoff.enforcements, guard.backoff_secs
+    );
+    if guard.notify && OSCILLATION.lock().unwrap().should_notify(&app) {
+        show_balloon_tip(
+            main_window(),
+            "检测到输入法拉锯",
+            &format!(
+                "{} 反复切回输入法，已暂停对该窗口的强制 {} 秒",
+                app, guard.backoff_secs
+            ),
+        );
+    }
+}
+
+// 读取窗口当前是否已处于目标模式，无法判断时返回 None
+unsafe fn window_in_mode(hwnd: HWND, ime_mode: &ImeMode) -> Option<bool> {
+    let strategy = CONFIG.read().unwrap().enforce_strategy.clone();
+    match strategy {
+        EnforceStrategy::KeyboardLayout => {
+            let thread_id = GetWindowThreadProcessId(hwnd, None);
+            if thread_id == 0 {
+                return None;
+            }
+            let lang_id = GetKeyboardLayout(thread_id).0 as usize & 0xFFFF;
+            Some(match ime_mode {
+                ImeMode::ChineseOnly => lang_id == 0x0804,
+                ImeMode::EnglishOnly => lang_id == 0x0409,
+            })
+        }
+        EnforceStrategy::Keystroke => ImmConversionState::new(hwnd)
+            .is_chinese()
+            .map(|chinese| chinese == (*ime_mode == ImeMode::ChineseOnly)),
+    }
+}
+
+// 按配置的策略强制输入法模式
+pub unsafe fn enforce_ime_mode(hwnd: HWND, ime_mode: &ImeMode) {
+    let config = CONFIG.read().unwrap();
+    let strategy = config.enforce_strategy.clone();
+    let toggle_keys = config.ime_toggle_keys.clone();
+    drop(config);
+
+    match strategy {
+        EnforceStrategy::KeyboardLayout => enforce_global_ime_mode(hwnd, ime_mode),
+        EnforceStrategy::Keystroke => enforce_by_keystroke(ime_mode, &toggle_keys),
+    }
+}
+
+// 模拟输入法自身的切换键（按键总是发往前台窗口）
+unsafe fn enforce_by_keystroke(ime_mode: &ImeMode, toggle_keys: &HashMap<String, String>) {
+    let target = GetForegroundWindow();
+    let thread_id = GetWindowThreadProcessId(target, None);
+    let klid = format!("{:08X}", GetKeyboardLayout(thread_id).0 as usize as u32);
+
+    let sequence_str = toggle_keys
+        .iter()
+        .find(|(ime, _)| ime.eq_ignore_ascii_case(&klid))
+        .or_else(|| {
+            toggle_keys
+                .iter()
+                .find(|(ime, _)| ime.as_str() == "default")
+        })
+        .map(|(_, keys)| keys);
+    let Some(sequence_str) = sequence_str else {
+        warn!("未配置输入法 {} 的切换键，跳过模拟按键", klid);
+        return;
+    };
+    let Some(sequence) = parse_key_sequence(sequence_str) else {
+        error!("无效的切换键序列: {} (输入法 {})", sequence_str, klid);
+        return;
+    };
+
+    let mut enforcer = KeystrokeEnforcer::new(sequence, *ime_mode == ImeMode::ChineseOnly);
+    let step = enforcer.start(&mut ImmConversionState::new(target), &mut SendInputInjector);
+    drive_keystroke(
+        PendingKeystroke {
+            target: target.0 as usize,
+            klid,
+            enforcer,
+        },
+        step,
+    );
+}
+
+// 等待输入法响应后验证，直到强制结束。
+// 在主窗口线程（热键、托盘菜单）中通过定时器等待，避免阻塞界面；在后台线程中直接等待
+fn drive_keystroke(mut pending: PendingKeystroke, mut step: KeystrokeStep) {
+    loop {
+        match step {
+            KeystrokeStep::Done(outcome) => {
+                info!("模拟按键强制结果 (输入法 {}): {:?}", pending.klid, outcome);
+                return;
+            }
+            KeystrokeStep::Wait(settle) => unsafe {
+                let hwnd = main_window();
+                if GetWindowThreadProcessId(hwnd, None) == GetCurrentThreadId() {
+                    *PENDING_KEYSTROKE.lock().unwrap() = Some(pending);
+                    SetTimer(
+                        Some(hwnd),
+                        constants::TIMER_ID_KEYSTROKE_VERIFY,
+                        settle.as_millis() as u32,
+                        None,
+                    );
+                    return;
+                }
+                std::thread::sleep(settle);
+                step = verify_keystroke(&mut pending);
+            },
+        }
+    }
+}
+
+fn verify_keystroke(pending: &mut PendingKeystroke) -> KeystrokeStep {
+    let target = HWND(pending.target as *mut std::ffi::c_void);
+    pending
+        .enforcer
+        .verify(&mut ImmConversionState::new(target), &mut SendInputInjector)
+}
+
+// 模拟按键后的等待结束，继续验证
+pub fn continue_keystroke_enforcement(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(Some(hwnd), constants::TIMER_ID_KEYSTROKE_VERIFY);
+    }
+    let pending = PENDING_KEYSTROKE.lock().unwrap().take();
+    if let Some(mut pending) = pending {
+        let step = verify_keystroke(&mut pending);
+        drive_keystroke(pending, step);
+    }
+}
+
 pub unsafe fn enforce_global_ime_mode(hwnd: HWND, ime_mode: &ImeMode) {
     // 获取目标键盘布局
     let target_hkl = match ime_mode {
@@ -0,0 +1,200 @@
+use crate::config::{Action, CapsLockMode};
+use crate::constants;
+use crate::hooks::suspend::is_suspended;
+use crate::ime::keystroke::{InputInjector, KeyEvent};
+use crate::ime::win_input::SendInputInjector;
+use crate::keyboard::capslock::{CapsLockMachine, VK_CAPITAL};
+use crate::keyboard::chord::{ChordBinding, ChordMachine};
+use crate::keyboard::double_tap::{DoubleTapBinding, DoubleTapMachine};
+use crate::keyboard::side_hotkey::{SideHotkeyBinding, SideHotkeyMachine};
+use crate::keyboard::tap_hold::{Effect, KeyInput, TapBinding, TapHoldMachine};
+use crate::tray::main_window;
+use crate::utils::parse_hotkey::Hotkey;
+use crate::utils::trigger::Trigger;
+use lazy_static::lazy_static;
+use log::{error, info};
+use std::sync::Mutex;
+use windows::Win32::{
+    Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM},
+    System::LibraryLoader::GetModuleHandleW,
+    UI::{Input::KeyboardAndMouse::GetKeyState, WindowsAndMessaging::*},
+};
+
+// 低级键盘钩子：处理 RegisterHotKey 无法表达的触发方式（如轻按/按住、双击、区分左右修饰键）
+struct KeyboardHookState {
+    hook: Option<usize>, // HHOOK
+    machine: TapHoldMachine,
+    double_tap: DoubleTapMachine,
+    chord: ChordMachine,
+    side_hotkey: SideHotkeyMachine,
+    capslock: CapsLockMachine,
+    actions: Vec<Action>,
+}
+
+lazy_static! {
+    static ref KEYBOARD_HOOK: Mutex<KeyboardHookState> = Mutex::new(KeyboardHookState {
+        hook: None,
+        machine: TapHoldMachine::new(Vec::new(), 0),
+        double_tap: DoubleTapMachine::new(Vec::new(), 0, 0),
+        chord: ChordMachine::new(Vec::new(), 0),
+        side_hotkey: SideHotkeyMachine::new(Vec::new()),
+        capslock: CapsLockMachine::new(CapsLockMode::Native, 0, None),
+        actions: Vec::new(),
+    });
+}
+
+// 键盘钩子各触发方式的时间参数（毫秒）
+pub struct HookSettings {
+    pub tap_timeout_ms: u32,
+    pub double_tap_interval_ms: u32,
+    pub chord_timeout_ms: u32,
+    pub capslock_mode: CapsLockMode,
+    pub capslock_long_press_ms: u32,
+}
+
+// 设置由键盘钩子处理的绑定；有绑定时安装钩子，没有时卸载（需在主线程调用）
+pub fn set_hook_bindings(bindings: Vec<(Trigger, Action)>, settings: &HookSettings) {
+    let mut state = KEYBOARD_HOOK.lock().unwrap();
+
+    let mut taps = Vec::new();
+    let mut double_taps = Vec::new();
+    let mut chords = Vec::new();
+    let mut side_hotkeys = Vec::new();
+    let mut capslock = None;
+    let mut actions = Vec::new();
+    for (trigger, action) in bindings {
+        let id = actions.len();
+        match trigger {
+            Trigger::Tap { key, hold } => taps.push(TapBinding { key, hold, id }),
+            Trigger::DoubleTap { key } => double_taps.push(DoubleTapBinding { key, id }),
+            Trigger::Chord { prefix, key } => chords.push(ChordBinding { prefix, key, id }),
+            Trigger::Hotkey(hotkey) if is_capslock(&hotkey, settings.capslock_mode) => {
+                capslock = Some(id)
+            }
+            Trigger::Hotkey(hotkey) if hotkey.is_side_specific() => {
+                side_hotkeys.push(SideHotkeyBinding { hotkey, id })
+            }
+            Trigger::Hotkey(_) | Trigger::Mouse { .. } => continue,
+        }
+        info!("键盘钩子绑定: {} -> {}", trigger, action.description());
+        actions.push(action);
+    }
+
+    let needs_hook = !actions.is_empty();
+    state.machine = TapHoldMachine::new(taps, settings.tap_timeout_ms);
+    state.double_tap = DoubleTapMachine::new(
+        double_taps,
+        settings.tap_timeout_ms,
+        settings.double_tap_interval_ms,
+    );
+    state.chord = ChordMachine::new(chords, settings.chord_timeout_ms);
+    state.side_hotkey = SideHotkeyMachine::new(side_hotkeys);
+    state.capslock = CapsLockMachine::new(
+        settings.capslock_mode,
+        settings.capslock_long_press_ms,
+        capslock,
+    );
+    state.actions = actions;
+
+    match (needs_hook, state.hook) {
+        (true, None) => unsafe {
+            let instance = GetModuleHandleW(None).ok();
+            match SetWindowsHookExW(
+                WH_KEYBOARD_LL,
+                Some(keyboard_hook_proc),
+                instance.map(|h| HINSTANCE(h.0)),
+                0,
+            ) {
+                Ok(hook) => {
+                    info!("低级键盘钩子已安装");
+                    state.hook = Some(hook.0 as usize);
+                }
+                Err(e) => error!("安装低级键盘钩子失败: {}", e),
+            }
+        },
+        (false, Some(hook)) => {
+            unhook(hook);
+            state.hook = None;
+        }
+        _ => {}
+    }
+}
+
+// 单独的 CapsLock 热键在非 Native 模式下由键盘钩子处理，以保留大小写锁定功能
+pub fn is_capslock(hotkey: &Hotkey, mode: CapsLockMode) -> bool {
+    mode != CapsLockMode::Native && *hotkey == Hotkey::new(0, VK_CAPITAL)
+}
+
+// 卸载键盘钩子
+pub fn remove_keyboard_hook() {
+    let mut state = KEYBOARD_HOOK.lock().unwrap();
+    if let Some(hook) = state.hook.take() {
+        unhook(hook);
+    }
+}
+
+fn unhook(hook: usize) {
+    unsafe {
+        if UnhookWindowsHookEx(HHOOK(hook as *mut core::ffi::c_void)).is_ok() {
+            info!("低级键盘钩子已卸载");
+        }
+    }
+}
+
+// 根据动作编号查找键盘钩子绑定的动作
+pub fn hook_action(id: usize) -> Option<Action> {
+    KEYBOARD_HOOK.lock().unwrap().actions.get(id).cloned()
+}
+
+unsafe extern "system" fn keyboard_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    // 游戏模式下不处理任何按键
+    if code == HC_ACTION as i32 && !is_suspended() {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        // 忽略本程序模拟的按键
+        if info.dwExtraInfo != constants::INJECTED_INPUT_MARKER {
+            let input = KeyInput {
+                vk: info.vkCode,
+                down: matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN),
+                time_ms: info.time,
+            };
+            let mut state = KEYBOARD_HOOK.lock().unwrap();
+            let mut decision = state.machine.handle(input);
+            if let Some(id) = state.double_tap.handle(input) {
+                decision.effects.push(Effect::Fire(id));
+            }
+            let caps_on = input.vk == VK_CAPITAL && GetKeyState(VK_CAPITAL as i32) & 1 != 0;
+            for other in [
+                state.chord.handle(input),
+                state.side_hotkey.handle(input),
+                state.capslock.handle(input, caps_on),
+            ] {
+                decision.swallow |= other.swallow;
+                decision.effects.extend(other.effects);
+            }
+            drop(state);
+
+            let mut injected: Vec<KeyEvent> = Vec::new();
+            for effect in decision.effects {
+                match effect {
+                    Effect::Fire(id) => {
+                        let _ = PostMessageW(
+                            Some(main_window()),
+                            constants::WM_HOOK_ACTION,
+                            WPARAM(id),
+                            LPARAM(0),
+                        );
+                    }
+                    Effect::Inject(event) => injected.push(event),
+                }
+            }
+            if !injected.is_empty() {
+                SendInputInjector.inject(&injected);
+            }
+
+            if decision.swallow {
+                return LRESULT(1);
+            }
+        }
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
@@ -1 +1,4 @@
-pub mod event_loop_hook;
\ No newline at end of file
+pub mod event_loop_hook;
+pub mod keyboard_hook;
+pub mod mouse_hook;
+pub mod suspend;
\ No newline at end of file
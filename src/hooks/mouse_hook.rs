@@ -0,0 +1,151 @@
+use crate::config::Action;
+use crate::constants;
+use crate::hooks::suspend::is_suspended;
+use crate::keyboard::modifier_bit;
+use crate::mouse::matcher::{MouseBinding, MouseInput, MouseMatcher};
+use crate::mouse::{x_button, MouseButton};
+use crate::tray::main_window;
+use crate::utils::trigger::Trigger;
+use lazy_static::lazy_static;
+use log::{error, info};
+use std::sync::Mutex;
+use windows::Win32::{
+    Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM},
+    System::LibraryLoader::GetModuleHandleW,
+    UI::{Input::KeyboardAndMouse::GetAsyncKeyState, WindowsAndMessaging::*},
+};
+
+// 低级鼠标钩子：处理鼠标按键及滚轮倾斜触发方式，未绑定的事件原样放行
+struct MouseHookState {
+    hook: Option<usize>, // HHOOK
+    matcher: MouseMatcher,
+    actions: Vec<Action>,
+}
+
+lazy_static! {
+    static ref MOUSE_HOOK: Mutex<MouseHookState> = Mutex::new(MouseHookState {
+        hook: None,
+        matcher: MouseMatcher::new(Vec::new()),
+        actions: Vec::new(),
+    });
+}
+
+// 设置鼠标绑定；有绑定时安装钩子，没有时卸载（需在主线程调用）
+pub fn set_mouse_bindings(bindings: Vec<(Trigger, Action)>) {
+    let mut state = MOUSE_HOOK.lock().unwrap();
+
+    let mut mouse_bindings = Vec::new();
+    let mut actions = Vec::new();
+    for (trigger, action) in bindings {
+        if let Trigger::Mouse { modifiers, button } = trigger {
+            info!("鼠标钩子绑定: {} -> {}", trigger, action.description());
+            mouse_bindings.push(MouseBinding {
+                modifiers,
+                button,
+                id: actions.len(),
+            });
+            actions.push(action);
+        }
+    }
+
+    state.matcher = MouseMatcher::new(mouse_bindings);
+    state.actions = actions;
+
+    match (state.matcher.is_empty(), state.hook) {
+        (false, None) => unsafe {
+            let instance = GetModuleHandleW(None).ok();
+            match SetWindowsHookExW(
+                WH_MOUSE_LL,
+                Some(mouse_hook_proc),
+                instance.map(|h| HINSTANCE(h.0)),
+                0,
+            ) {
+                Ok(hook) => {
+                    info!("低级鼠标钩子已安装");
+                    state.hook = Some(hook.0 as usize);
+                }
+                Err(e) => error!("安装低级鼠标钩子失败: {}", e),
+            }
+        },
+        (true, Some(hook)) => {
+            unhook(hook);
+            state.hook = None;
+        }
+        _ => {}
+    }
+}
+
+// 卸载鼠标钩子
+pub fn remove_mouse_hook() {
+    let mut state = MOUSE_HOOK.lock().unwrap();
+    if let Some(hook) = state.hook.take() {
+        unhook(hook);
+    }
+}
+
+fn unhook(hook: usize) {
+    unsafe {
+        if UnhookWindowsHookEx(HHOOK(hook as *mut core::ffi::c_void)).is_ok() {
+            info!("低级鼠标钩子已卸载");
+        }
+    }
+}
+
+// 根据动作编号查找鼠标钩子绑定的动作
+pub fn mouse_action(id: usize) -> Option<Action> {
+    MOUSE_HOOK.lock().unwrap().actions.get(id).cloned()
+}
+
+// 鼠标消息转换为绑定的按键，不支持的消息返回 None
+fn mouse_input(message: u32, info: &MSLLHOOKSTRUCT) -> Option<MouseInput> {
+    let high_word = (info.mouseData >> 16) as u16;
+    let (button, down) = match message {
+        WM_MBUTTONDOWN => (MouseButton::Middle, true),
+        WM_MBUTTONUP => (MouseButton::Middle, false),
+        WM_XBUTTONDOWN | WM_XBUTTONUP => (x_button(high_word)?, message == WM_XBUTTONDOWN),
+        // 滚轮倾斜：正值向右，负值向左
+        WM_MOUSEHWHEEL if (high_word as i16) > 0 => (MouseButton::WheelRight, true),
+        WM_MOUSEHWHEEL if (high_word as i16) < 0 => (MouseButton::WheelLeft, true),
+        _ => return None,
+    };
+    Some(MouseInput {
+        button,
+        down,
+        time_ms: info.time,
+    })
+}
+
+// 当前按下的修饰键（MOD_L*/MOD_R* 位）
+fn pressed_modifiers() -> u32 {
+    [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0x5B, 0x5C]
+        .into_iter()
+        .filter(|&vk| unsafe { GetAsyncKeyState(vk as i32) } < 0)
+        .filter_map(modifier_bit)
+        .fold(0, |pressed, bit| pressed | bit)
+}
+
+unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    // 游戏模式下不处理任何鼠标事件
+    if code == HC_ACTION as i32 && !is_suspended() {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        if let Some(input) = mouse_input(wparam.0 as u32, info) {
+            let decision = MOUSE_HOOK
+                .lock()
+                .unwrap()
+                .matcher
+                .handle(input, pressed_modifiers());
+            if let Some(id) = decision.fire {
+                let _ = PostMessageW(
+                    Some(main_window()),
+                    constants::WM_MOUSE_ACTION,
+                    WPARAM(id),
+                    LPARAM(0),
+                );
+            }
+            if decision.swallow {
+                return LRESULT(1);
+            }
+        }
+    }
+    CallNextHookEx(None, code, wparam, lparam)
+}
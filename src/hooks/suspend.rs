@@ -0,0 +1,113 @@
+use crate::config::CONFIG;
+use crate::constants;
+use crate::state::STATE;
+use crate::tray::icon::update_tray_icon;
+use crate::tray::main_window;
+use crate::utils::hot_key::{register_all_hotkeys, unregister_all_hotkeys};
+use crate::utils::window_process::process_name_of_window;
+use lazy_static::lazy_static;
+use log::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use windows::Win32::{
+    Foundation::HWND,
+    UI::{
+        Shell::{SHQueryUserNotificationState, QUNS_RUNNING_D3D_FULL_SCREEN},
+        WindowsAndMessaging::{GetForegroundWindow, KillTimer, SetTimer},
+    },
+};
+
+// 游戏模式：前台程序在 suspend_apps 中或处于独占全屏时，注销热键、忽略键盘钩子并暂停强制，
+// 切换到其他程序后恢复
+
+lazy_static! {
+    // 暂停原因（显示在托盘提示中）
+    static ref SUSPENDED_BY: Mutex<Option<String>> = Mutex::new(None);
+}
+
+// 供键盘钩子快速判断
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+// 独占全屏通常在程序获得焦点后才生效，延迟再检查一次
+const FULLSCREEN_RECHECK_MS: u32 = 1000;
+
+pub fn is_suspended() -> bool {
+    SUSPENDED.load(Ordering::Relaxed)
+}
+
+// 暂停原因，如 "game.exe" 或 "game.exe (全屏)"
+pub fn suspended_by() -> Option<String> {
+    SUSPENDED_BY.lock().unwrap().clone()
+}
+
+// 前台窗口变化时调用（需在主线程调用，以便注销/注册热键）
+pub fn update_suspension(hwnd: HWND) {
+    evaluate(hwnd, true);
+}
+
+// 延迟检查定时器到期
+pub fn recheck_suspension() {
+    unsafe {
+        let _ = KillTimer(Some(main_window()), constants::TIMER_ID_SUSPEND_CHECK);
+        evaluate(GetForegroundWindow(), false);
+    }
+}
+
+fn evaluate(hwnd: HWND, schedule_recheck: bool) {
+    let app = process_name_of_window(hwnd);
+    let config = CONFIG.read().unwrap();
+    let listed = app.as_deref().is_some_and(|app| config.is_suspend_app(app));
+    let check_fullscreen = config.suspend_in_fullscreen;
+    drop(config);
+
+    let reason = if listed {
+        app.clone()
+    } else if check_fullscreen && is_fullscreen_exclusive() {
+        Some(format!("{} (全屏)", app.as_deref().unwrap_or("未知程序")))
+    } else {
+        if check_fullscreen && schedule_recheck {
+            unsafe {
+                SetTimer(
+                    Some(main_window()),
+                    constants::TIMER_ID_SUSPEND_CHECK,
+                    FULLSCREEN_RECHECK_MS,
+                    None,
+                );
+            }
+        }
+        None
+    };
+
+    set_suspended(reason);
+}
+
+fn is_fullscreen_exclusive() -> bool {
+    unsafe { SHQueryUserNotificationState() }
+        .is_ok_and(|state| state == QUNS_RUNNING_D3D_FULL_SCREEN)
+}
+
+fn set_suspended(reason: Option<String>) {
+    let mut current = SUSPENDED_BY.lock().unwrap();
+    if *current == reason {
+        return;
+    }
+    let was_suspended = current.is_some();
+    *current = reason.clone();
+    drop(current);
+    SUSPENDED.store(reason.is_some(), Ordering::Relaxed);
+
+    let hwnd = main_window();
+    match (&reason, was_suspended) {
+        (Some(reason), false) => {
+            info!("进入游戏模式，暂停热键与强制: {}", reason);
+            unregister_all_hotkeys(hwnd);
+        }
+        (None, true) => {
+            info!("退出游戏模式，恢复热键与强制");
+            register_all_hotkeys(hwnd);
+        }
+        _ => {}
+    }
+
+    let _ = update_tray_icon(hwnd, STATE.read().unwrap().master_switch);
+}
@@ -0,0 +1,300 @@
+use crate::utils::parse_hotkey::parse_any_key;
+use std::time::Duration;
+
+// 模拟按键策略：适用于搜狗、QQ 拼音等忽略键盘布局 API、只响应自身切换键的输入法。
+// 先读取当前转换状态，仅在需要时模拟切换键，等待输入法响应后再次读取以验证结果。
+// 等待由调用方安排（定时器或后台线程），本模块不会阻塞
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyEvent {
+    pub vk: u16,
+    pub key_up: bool,
+}
+
+// 读取目标窗口输入法的中/英状态，无法读取时返回 None
+pub trait ConversionState {
+    fn is_chinese(&mut self) -> Option<bool>;
+}
+
+// 按键注入抽象，便于替换为模拟实现
+pub trait InputInjector {
+    fn inject(&mut self, events: &[KeyEvent]) -> bool;
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum KeystrokeOutcome {
+    AlreadyInMode,              // 已经是目标模式，未发送按键
+    Switched { attempts: u32 }, // 切换成功
+    Unverified,                 // 已发送按键，但无法读取状态进行验证
+    Failed { attempts: u32 },   // 多次尝试后仍不是目标模式
+    StateUnavailable,           // 无法读取当前状态，未发送按键
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum KeystrokeStep {
+    Wait(Duration),         // 已发送切换键，等待后调用 verify
+    Done(KeystrokeOutcome), // 强制结束
+}
+
+pub struct KeystrokeEnforcer {
+    sequence: Vec<KeyEvent>,
+    target_chinese: bool,
+    max_attempts: u32,
+    attempts: u32,    // 已发送切换键的次数
+    settle: Duration, // 发送按键后等待输入法响应的时间
+}
+
+impl KeystrokeEnforcer {
+    pub fn new(sequence: Vec<KeyEvent>, target_chinese: bool) -> Self {
+        Self {
+            sequence,
+            target_chinese,
+            max_attempts: 2,
+            attempts: 0,
+            settle: Duration::from_millis(50),
+        }
+    }
+
+    // 读取当前状态，不是目标模式时发送第一次切换键
+    pub fn start(
+        &mut self,
+        state: &mut impl ConversionState,
+        injector: &mut impl InputInjector,
+    ) -> KeystrokeStep {
+        match state.is_chinese() {
+            None => KeystrokeStep::Done(KeystrokeOutcome::StateUnavailable),
+            Some(current) if current == self.target_chinese => {
+                KeystrokeStep::Done(KeystrokeOutcome::AlreadyInMode)
+            }
+            Some(_) => self.inject(injector),
+        }
+    }
+
+    // 等待结束后验证结果，仍不是目标模式时重试，直到达到尝试次数
+    pub fn verify(
+        &mut self,
+        state: &mut impl ConversionState,
+        injector: &mut impl InputInjector,
+    ) -> KeystrokeStep {
+        match state.is_chinese() {
+            Some(current) if current == self.target_chinese => {
+                KeystrokeStep::Done(KeystrokeOutcome::Switched {
+                    attempts: self.attempts,
+                })
+            }
+            Some(_) if self.attempts < self.max_attempts => self.inject(injector),
+            Some(_) => KeystrokeStep::Done(KeystrokeOutcome::Failed {
+                attempts: self.attempts,
+            }),
+            None => KeystrokeStep::Done(KeystrokeOutcome::Unverified),
+        }
+    }
+
+    fn inject(&mut self, injector: &mut impl InputInjector) -> KeystrokeStep {
+        self.attempts += 1;
+        if !injector.inject(&self.sequence) {
+            return KeystrokeStep::Done(KeystrokeOutcome::Failed {
+                attempts: self.attempts,
+            });
+        }
+        KeystrokeStep::Wait(self.settle)
+    }
+}
+
+// 解析切换键序列，如 "Shift"、"Ctrl+Space"、"Ctrl+Shift, Shift"（逗号分隔多次按键）
+pub fn parse_key_sequence(sequence: &str) -> Option<Vec<KeyEvent>> {
+    let mut events = Vec::new();
+
+    for stroke in sequence.split(',') {
+        let keys = stroke
+            .split('+')
+            .map(|name| parse_any_key(name).map(|vk| vk as u16))
+            .collect::<Option<Vec<u16>>>()?;
+
+        // 依次按下，逆序松开
+        events.extend(keys.iter().map(|&vk| KeyEvent { vk, key_up: false }));
+        events.extend(keys.iter().rev().map(|&vk| KeyEvent { vk, key_up: true }));
+    }
+
+    Some(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // 模拟的输入法：每收到一次切换键切换一次中/英，可设置前几次忽略切换键
+    struct FakeIme {
+        chinese: Rc<Cell<Option<bool>>>,
+    }
+
+    impl ConversionState for FakeIme {
+        fn is_chinese(&mut self) -> Option<bool> {
+            self.chinese.get()
+        }
+    }
+
+    struct FakeInjector {
+        chinese: Rc<Cell<Option<bool>>>,
+        ignored: u32, // 忽略的切换键次数
+        fail: bool,   // 注入失败
+        injected: Vec<Vec<KeyEvent>>,
+    }
+
+    impl InputInjector for FakeInjector {
+        fn inject(&mut self, events: &[KeyEvent]) -> bool {
+            if self.fail {
+                return false;
+            }
+            self.injected.push(events.to_vec());
+            if self.ignored > 0 {
+                self.ignored -= 1;
+            } else {
+                self.chinese.set(self.chinese.get().map(|chinese| !chinese));
+            }
+            true
+        }
+    }
+
+    fn fake(chinese: Option<bool>, ignored: u32) -> (FakeIme, FakeInjector) {
+        let chinese = Rc::new(Cell::new(chinese));
+        let state = FakeIme {
+            chinese: chinese.clone(),
+        };
+        let injector = FakeInjector {
+            chinese,
+            ignored,
+            fail: false,
+            injected: Vec::new(),
+        };
+        (state, injector)
+    }
+
+    fn shift() -> Vec<KeyEvent> {
+        parse_key_sequence("Shift").unwrap()
+    }
+
+    // 按调用方的方式驱动到结束，返回结果和验证次数
+    fn run(
+        enforcer: &mut KeystrokeEnforcer,
+        state: &mut FakeIme,
+        injector: &mut FakeInjector,
+    ) -> (KeystrokeOutcome, u32) {
+        let mut step = enforcer.start(state, injector);
+        let mut verifies = 0;
+        loop {
+            match step {
+                KeystrokeStep::Wait(_) => {
+                    verifies += 1;
+                    step = enforcer.verify(state, injector);
+                }
+                KeystrokeStep::Done(outcome) => return (outcome, verifies),
+            }
+        }
+    }
+
+    #[test]
+    fn already_in_mode_sends_nothing() {
+        let (mut state, mut injector) = fake(Some(true), 0);
+        let mut enforcer = KeystrokeEnforcer::new(shift(), true);
+        let step = enforcer.start(&mut state, &mut injector);
+        assert_eq!(step, KeystrokeStep::Done(KeystrokeOutcome::AlreadyInMode));
+        assert!(injector.injected.is_empty());
+    }
+
+    #[test]
+    fn switches_on_first_try() {
+        let (mut state, mut injector) = fake(Some(false), 0);
+        let mut enforcer = KeystrokeEnforcer::new(shift(), true);
+        let step = enforcer.start(&mut state, &mut injector);
+        assert_eq!(step, KeystrokeStep::Wait(Duration::from_millis(50)));
+        assert_eq!(
+            enforcer.verify(&mut state, &mut injector),
+            KeystrokeStep::Done(KeystrokeOutcome::Switched { attempts: 1 })
+        );
+        assert_eq!(injector.injected, vec![shift()]);
+    }
+
+    #[test]
+    fn switches_on_retry() {
+        let (mut state, mut injector) = fake(Some(true), 1);
+        let mut enforcer = KeystrokeEnforcer::new(shift(), false);
+        let outcome = run(&mut enforcer, &mut state, &mut injector);
+        assert_eq!(outcome, (KeystrokeOutcome::Switched { attempts: 2 }, 2));
+        assert_eq!(injector.injected.len(), 2);
+        assert_eq!(state.chinese.get(), Some(false));
+    }
+
+    #[test]
+    fn gives_up_after_two_attempts() {
+        let (mut state, mut injector) = fake(Some(false), u32::MAX);
+        let mut enforcer = KeystrokeEnforcer::new(shift(), true);
+        let outcome = run(&mut enforcer, &mut state, &mut injector);
+        assert_eq!(outcome, (KeystrokeOutcome::Failed { attempts: 2 }, 2));
+        assert_eq!(injector.injected.len(), 2);
+    }
+
+    #[test]
+    fn injection_failure_stops_immediately() {
+        let (mut state, mut injector) = fake(Some(false), 0);
+        injector.fail = true;
+        let mut enforcer = KeystrokeEnforcer::new(shift(), true);
+        let outcome = run(&mut enforcer, &mut state, &mut injector);
+        assert_eq!(outcome, (KeystrokeOutcome::Failed { attempts: 1 }, 0));
+    }
+
+    #[test]
+    fn unreadable_state() {
+        let (mut state, mut injector) = fake(None, 0);
+        let mut enforcer = KeystrokeEnforcer::new(shift(), true);
+        let outcome = run(&mut enforcer, &mut state, &mut injector);
+        assert_eq!(outcome, (KeystrokeOutcome::StateUnavailable, 0));
+        assert!(injector.injected.is_empty());
+
+        // 发送按键后无法读取状态
+        let (mut state, mut injector) = fake(Some(false), 0);
+        let mut enforcer = KeystrokeEnforcer::new(shift(), true);
+        enforcer.start(&mut state, &mut injector);
+        state.chinese.set(None);
+        assert_eq!(
+            enforcer.verify(&mut state, &mut injector),
+            KeystrokeStep::Done(KeystrokeOutcome::Unverified)
+        );
+    }
+
+    #[test]
+    fn parses_key_sequences() {
+        let down = |vk| KeyEvent { vk, key_up: false };
+        let up = |vk| KeyEvent { vk, key_up: true };
+        assert_eq!(
+            parse_key_sequence("Shift"),
+            Some(vec![down(0x10), up(0x10)])
+        );
+        assert_eq!(
+            parse_key_sequence("Ctrl+Space"),
+            Some(vec![down(0x11), down(0x20), up(0x20), up(0x11)])
+        );
+        assert_eq!(
+            parse_key_sequence("LCtrl+LShift, RShift"),
+            Some(vec![
+                down(0xA2),
+                down(0xA0),
+                up(0xA0),
+                up(0xA2),
+                down(0xA1),
+                up(0xA1)
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_sequences() {
+        assert_eq!(parse_key_sequence(""), None);
+        assert_eq!(parse_key_sequence("Shfit"), None);
+        assert_eq!(parse_key_sequence("Ctrl+"), None);
+        assert_eq!(parse_key_sequence("Shift,"), None);
+        assert_eq!(parse_key_sequence("Ctrl+Space,,Shift"), None);
+    }
+}
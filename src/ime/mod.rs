@@ -0,0 +1,4 @@
+pub mod keystroke;
+pub mod oscillation;
+pub mod pinyin_settings;
+pub mod win_input;
@@ -0,0 +1,196 @@
+use crate::config::OscillationGuard;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+// 拉锯检测：同一窗口在短时间内被反复强制（说明每次都被其他程序或应用自身切回），
+// 达到阈值后对该窗口退避一段时间，避免无休止地对抗并刷屏日志。
+
+#[derive(Default)]
+struct WindowHistory {
+    enforcements: VecDeque<Instant>,
+    backoff_until: Option<Instant>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Backoff {
+    pub enforcements: usize,
+    pub until: Instant,
+}
+
+pub struct OscillationDetector<K> {
+    windows: HashMap<K, WindowHistory>,
+    notified_apps: HashSet<String>,
+}
+
+impl<K: Eq + Hash> OscillationDetector<K> {
+    pub fn new() -> Self {
+        Self {
+            windows: HashMap::new(),
+            notified_apps: HashSet::new(),
+        }
+    }
+
+    // 该窗口是否处于退避期，过期的退避会被清除
+    pub fn is_backing_off(&mut self, key: &K, now: Instant) -> bool {
+        let Some(history) = self.windows.get_mut(key) else {
+            return false;
+        };
+        match history.backoff_until {
+            Some(until) if now < until => true,
+            Some(_) => {
+                history.backoff_until = None;
+                history.enforcements.clear();
+                false
+            }
+            None => false,
+        }
+    }
+
+    // 记录一次实际生效的强制，达到阈值时开始退避并返回退避信息
+    pub fn record_enforcement(
+        &mut self,
+        key: K,
+        now: Instant,
+        guard: &OscillationGuard,
+    ) -> Option<Backoff> {
+        let within = Duration::from_secs(guard.window_secs);
+        self.prune(now, within);
+
+        let history = self.windows.entry(key).or_default();
+        history.enforcements.push_back(now);
+        while let Some(&first) = history.enforcements.front() {
+            if now.duration_since(first) > within {
+                history.enforcements.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if history.enforcements.len() < guard.max_enforcements.max(1) {
+            return None;
+        }
+
+        let backoff = Backoff {
+            enforcements: history.enforcements.len(),
+            until: now + Duration::from_secs(guard.backoff_secs),
+        };
+        history.backoff_until = Some(backoff.until);
+        history.enforcements.clear();
+        Some(backoff)
+    }
+
+    // 每个程序只通知一次，首次调用返回 true
+    pub fn should_notify(&mut self, app: &str) -> bool {
+        self.notified_apps.insert(app.to_lowercase())
+    }
+
+    // 清理已不活跃的窗口记录，避免随窗口句柄无限增长
+    fn prune(&mut self, now: Instant, within: Duration) {
+        self.windows.retain(|_, history| {
+            let backing_off = history.backoff_until.is_some_and(|until| now < until);
+            let recent = history
+                .enforcements
+                .back()
+                .is_some_and(|&last| now.duration_since(last) <= within);
+            backing_off || recent
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> OscillationGuard {
+        OscillationGuard {
+            max_enforcements: 3,
+            window_secs: 10,
+            backoff_secs: 60,
+            ..OscillationGuard::default()
+        }
+    }
+
+    fn secs(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn backs_off_after_max_enforcements_within_window() {
+        let start = Instant::now();
+        let mut detector = OscillationDetector::new();
+        assert!(detector.record_enforcement(1, start, &guard()).is_none());
+        assert!(detector
+            .record_enforcement(1, secs(start, 4), &guard())
+            .is_none());
+        let backoff = detector
+            .record_enforcement(1, secs(start, 8), &guard())
+            .unwrap();
+        assert_eq!(backoff.enforcements, 3);
+        assert_eq!(backoff.until, secs(start, 68));
+        assert!(detector.is_backing_off(&1, secs(start, 9)));
+        // 其他窗口不受影响
+        assert!(!detector.is_backing_off(&2, secs(start, 9)));
+    }
+
+    #[test]
+    fn enforcements_spread_beyond_window_do_not_back_off() {
+        let start = Instant::now();
+        let mut detector = OscillationDetector::new();
+        for at in [0, 6, 12, 18, 24] {
+            assert!(detector
+                .record_enforcement(1, secs(start, at), &guard())
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn backoff_expires_and_clears_history() {
+        let start = Instant::now();
+        let mut detector = OscillationDetector::new();
+        for at in [0, 1, 2] {
+            detector.record_enforcement(1, secs(start, at), &guard());
+        }
+        assert!(detector.is_backing_off(&1, secs(start, 61)));
+        assert!(!detector.is_backing_off(&1, secs(start, 62)));
+
+        // 退避前的强制记录已清除，需要重新累计
+        assert!(detector
+            .record_enforcement(1, secs(start, 63), &guard())
+            .is_none());
+        assert!(detector
+            .record_enforcement(1, secs(start, 64), &guard())
+            .is_none());
+        assert!(detector
+            .record_enforcement(1, secs(start, 65), &guard())
+            .is_some());
+    }
+
+    #[test]
+    fn prune_drops_idle_windows() {
+        let start = Instant::now();
+        let mut detector = OscillationDetector::new();
+        detector.record_enforcement(1, start, &guard());
+        for at in [0, 1, 2] {
+            detector.record_enforcement(2, secs(start, at), &guard());
+        }
+
+        detector.record_enforcement(3, secs(start, 11), &guard());
+        assert!(!detector.windows.contains_key(&1));
+        // 处于退避期的窗口保留
+        assert!(detector.windows.contains_key(&2));
+        assert!(detector.windows.contains_key(&3));
+
+        detector.record_enforcement(3, secs(start, 80), &guard());
+        assert!(!detector.windows.contains_key(&2));
+    }
+
+    #[test]
+    fn notifies_once_per_app() {
+        let mut detector = OscillationDetector::<usize>::new();
+        assert!(detector.should_notify("WeChat.exe"));
+        assert!(!detector.should_notify("wechat.EXE"));
+        assert!(detector.should_notify("Code.exe"));
+        assert!(!detector.should_notify("Code.exe"));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::config::{Config, ImeMode, PinyinSettings};
+use log::{error, info};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use winreg::enums::*;
+use winreg::RegKey;
+
+// 微软拼音的用户设置位于 HKCU 下，修改前记录原值以便卸载时还原
+const PINYIN_SETTINGS_KEY: &str = "Software\\Microsoft\\InputMethod\\Settings\\CHS";
+const BACKUP_FILE_NAME: &str = "pinyin_backup.json";
+
+pub const VALUE_DEFAULT_MODE: &str = "Default Mode"; // 0 = 中文, 1 = 英文
+pub const VALUE_ENGLISH_SWITCH_KEY: &str = "English Switch Key"; // 1 = Shift, 4 = 无
+pub const VALUE_CTRL_SPACE: &str = "Enable Ctrl Space"; // 0 = 关闭, 1 = 开启
+
+// 原值记录：None 表示原本不存在该值，还原时删除
+pub type PinyinBackup = BTreeMap<String, Option<u32>>;
+
+// 注册表读写抽象，便于替换为内存实现
+pub trait RegistryStore {
+    fn get_dword(&self, name: &str) -> Option<u32>;
+    fn set_dword(&mut self, name: &str, value: u32) -> io::Result<()>;
+    fn delete_value(&mut self, name: &str) -> io::Result<()>;
+}
+
+impl PinyinSettings {
+    // 转换为需要写入的注册表值
+    pub fn registry_values(&self) -> BTreeMap<String, u32> {
+        let mut values = self.raw_values.clone();
+        if let Some(mode) = &self.default_mode {
+            let value = match mode {
+                ImeMode::ChineseOnly => 0,
+                ImeMode::EnglishOnly => 1,
+            };
+            values.insert(VALUE_DEFAULT_MODE.to_string(), value);
+        }
+        if let Some(enabled) = self.shift_toggle {
+            values.insert(
+                VALUE_ENGLISH_SWITCH_KEY.to_string(),
+                if enabled { 1 } else { 4 },
+            );
+        }
+        if let Some(enabled) = self.ctrl_space_toggle {
+            values.insert(VALUE_CTRL_SPACE.to_string(), enabled as u32);
+        }
+        values
+    }
+}
+
+// 应用配置的设置：首次修改某个值时记录原值；配置中已移除的值恢复原值。返回发生变化的值名
+pub fn apply_settings(
+    store: &mut impl RegistryStore,
+    desired: &BTreeMap<String, u32>,
+    backup: &mut PinyinBackup,
+) -> io::Result<Vec<String>> {
+    let mut changed = Vec::new();
+
+    let removed: Vec<String> = backup
+        .keys()
+        .filter(|name| !desired.contains_key(*name))
+        .cloned()
+        .collect();
+    for name in removed {
+        restore_value(store, &name, backup[&name])?;
+        backup.remove(&name);
+        changed.push(name);
+    }
+
+    for (name, &value) in desired {
+        let current = store.get_dword(name);
+        if current == Some(value) {
+            continue;
+        }
+        backup.entry(name.clone()).or_insert(current);
+        store.set_dword(name, value)?;
+        changed.push(name.clone());
+    }
+
+    Ok(changed)
+}
+
+// 还原所有记录过的原值
+pub fn revert_settings(
+    store: &mut impl RegistryStore,
+    backup: &mut PinyinBackup,
+) -> io::Result<()> {
+    while let Some((name, original)) = backup.pop_first() {
+        if let Err(e) = restore_value(store, &name, original) {
+            backup.insert(name, original);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+fn restore_value(
+    store: &mut impl RegistryStore,
+    name: &str,
+    original: Option<u32>,
+) -> io::Result<()> {
+    match original {
+        Some(value) => store.set_dword(name, value),
+        None => store.delete_value(name),
+    }
+}
+
+// 微软拼音设置所在的注册表键
+pub struct WinRegStore {
+    key: RegKey,
+}
+
+impl WinRegStore {
+    pub fn open() -> io::Result<Self> {
+        let (key, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(PINYIN_SETTINGS_KEY)?;
+        Ok(Self { key })
+    }
+}
+
+impl RegistryStore for WinRegStore {
+    fn get_dword(&self, name: &str) -> Option<u32> {
+        self.key.get_value::<u32, _>(name).ok()
+    }
+
+    fn set_dword(&mut self, name: &str, value: u32) -> io::Result<()> {
+        self.key.set_value(name, &value)
+    }
+
+    fn delete_value(&mut self, name: &str) -> io::Result<()> {
+        match self.key.delete_value(name) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+fn backup_path() -> PathBuf {
+    Config::config_dir().join(BACKUP_FILE_NAME)
+}
+
+fn load_backup() -> PinyinBackup {
+    fs::read_to_string(backup_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup(backup: &PinyinBackup) -> io::Result<()> {
+    let path = backup_path();
+    if backup.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        };
+    }
+    fs::write(path, serde_json::to_string_pretty(backup)?)
+}
+
+// 启动及重新加载配置时应用微软拼音设置
+pub fn apply_pinyin_settings(settings: &PinyinSettings) {
+    let mut store = match WinRegStore::open() {
+        Ok(store) => store,
+        Err(e) => {
+            error!("无法打开微软拼音设置注册表键: {}", e);
+            return;
+        }
+    };
+
+    let mut backup = load_backup();
+    let result = apply_settings(&mut store, &settings.registry_values(), &mut backup);
+    if let Err(e) = save_backup(&backup) {
+        error!("保存微软拼音设置原值失败: {}", e);
+    }
+    match result {
+        Ok(changed) if changed.is_empty() => {}
+        Ok(changed) => info!("已更新微软拼音设置: {:?}", changed),
+        Err(e) => error!("写入微软拼音设置失败: {}", e),
+    }
+}
+
+// 卸载时还原微软拼音设置
+pub fn revert_pinyin_settings() {
+    let mut backup = load_backup();
+    if backup.is_empty() {
+        return;
+    }
+
+    let result = WinRegStore::open().and_then(|mut store| revert_settings(&mut store, &mut backup));
+    if let Err(e) = save_backup(&backup) {
+        error!("保存微软拼音设置原值失败: {}", e);
+    }
+    match result {
+        Ok(()) => info!("已还原微软拼音设置"),
+        Err(e) => error!("还原微软拼音设置失败: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // 内存中的注册表键，可设置写入失败
+    #[derive(Default)]
+    struct FakeStore {
+        values: HashMap<String, u32>,
+        fail_writes: bool,
+    }
+
+    impl RegistryStore for FakeStore {
+        fn get_dword(&self, name: &str) -> Option<u32> {
+            self.values.get(name).copied()
+        }
+
+        fn set_dword(&mut self, name: &str, value: u32) -> io::Result<()> {
+            if self.fail_writes {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "拒绝访问"));
+            }
+            self.values.insert(name.to_string(), value);
+            Ok(())
+        }
+
+        fn delete_value(&mut self, name: &str) -> io::Result<()> {
+            self.values.remove(name);
+            Ok(())
+        }
+    }
+
+    fn store(values: &[(&str, u32)]) -> FakeStore {
+        FakeStore {
+            values: values.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+            fail_writes: false,
+        }
+    }
+
+    fn desired(values: &[(&str, u32)]) -> BTreeMap<String, u32> {
+        values.iter().map(|&(k, v)| (k.to_string(), v)).collect()
+    }
+
+    #[test]
+    fn apply_records_backup_then_writes() {
+        let mut store = store(&[(VALUE_DEFAULT_MODE, 0)]);
+        let mut backup = PinyinBackup::new();
+        let changed = apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1), (VALUE_CTRL_SPACE, 0)]),
+            &mut backup,
+        )
+        .unwrap();
+
+        assert_eq!(changed, vec![VALUE_DEFAULT_MODE, VALUE_CTRL_SPACE]);
+        assert_eq!(store.get_dword(VALUE_DEFAULT_MODE), Some(1));
+        assert_eq!(store.get_dword(VALUE_CTRL_SPACE), Some(0));
+        assert_eq!(backup[VALUE_DEFAULT_MODE], Some(0));
+        assert_eq!(backup[VALUE_CTRL_SPACE], None);
+    }
+
+    #[test]
+    fn backup_is_kept_when_write_fails() {
+        let mut store = store(&[(VALUE_DEFAULT_MODE, 0)]);
+        store.fail_writes = true;
+        let mut backup = PinyinBackup::new();
+        let result = apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1)]),
+            &mut backup,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(backup[VALUE_DEFAULT_MODE], Some(0));
+        assert_eq!(store.get_dword(VALUE_DEFAULT_MODE), Some(0));
+    }
+
+    #[test]
+    fn unchanged_values_are_not_backed_up() {
+        let mut store = store(&[(VALUE_DEFAULT_MODE, 1)]);
+        let mut backup = PinyinBackup::new();
+        let changed = apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1)]),
+            &mut backup,
+        )
+        .unwrap();
+        assert!(changed.is_empty());
+        assert!(backup.is_empty());
+    }
+
+    #[test]
+    fn revert_restores_and_deletes() {
+        let mut store = store(&[(VALUE_DEFAULT_MODE, 0), ("Other", 7)]);
+        let mut backup = PinyinBackup::new();
+        apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1), (VALUE_ENGLISH_SWITCH_KEY, 4)]),
+            &mut backup,
+        )
+        .unwrap();
+
+        revert_settings(&mut store, &mut backup).unwrap();
+        assert_eq!(
+            store.values,
+            self::store(&[(VALUE_DEFAULT_MODE, 0), ("Other", 7)]).values
+        );
+        assert!(backup.is_empty());
+    }
+
+    #[test]
+    fn second_apply_keeps_original_backup() {
+        let mut store = store(&[(VALUE_DEFAULT_MODE, 0)]);
+        let mut backup = PinyinBackup::new();
+        apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1)]),
+            &mut backup,
+        )
+        .unwrap();
+        // 用户在外部改了值，下次启动时再次应用
+        store.set_dword(VALUE_DEFAULT_MODE, 0).unwrap();
+        apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1)]),
+            &mut backup,
+        )
+        .unwrap();
+        assert_eq!(backup[VALUE_DEFAULT_MODE], Some(0));
+
+        store.set_dword(VALUE_DEFAULT_MODE, 5).unwrap();
+        apply_settings(
+            &mut store,
+            &desired(&[(VALUE_DEFAULT_MODE, 1)]),
+            &mut backup,
+        )
+        .unwrap();
+        assert_eq!(backup[VALUE_DEFAULT_MODE], Some(0));
+
+        revert_settings(&mut store, &mut backup).unwrap();
+        assert_eq!(store.get_dword(VALUE_DEFAULT_MODE), Some(0));
+    }
+
+    #[test]
+    fn removed_settings_are_restored_on_apply() {
+        let mut store = store(&[]);
+        let mut backup = PinyinBackup::new();
+        apply_settings(&mut store, &desired(&[(VALUE_CTRL_SPACE, 1)]), &mut backup).unwrap();
+        let changed = apply_settings(&mut store, &desired(&[]), &mut backup).unwrap();
+        assert_eq!(changed, vec![VALUE_CTRL_SPACE]);
+        assert_eq!(store.get_dword(VALUE_CTRL_SPACE), None);
+        assert!(backup.is_empty());
+    }
+
+    #[test]
+    fn settings_map_to_registry_values() {
+        let settings = PinyinSettings {
+            default_mode: Some(ImeMode::EnglishOnly),
+            shift_toggle: Some(false),
+            ctrl_space_toggle: Some(true),
+            raw_values: desired(&[("Other", 3)]),
+        };
+        assert_eq!(
+            settings.registry_values(),
+            desired(&[
+                (VALUE_DEFAULT_MODE, 1),
+                (VALUE_ENGLISH_SWITCH_KEY, 4),
+                (VALUE_CTRL_SPACE, 1),
+                ("Other", 3),
+            ])
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::constants;
+use crate::ime::keystroke::{ConversionState, InputInjector, KeyEvent};
+use log::error;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    UI::{Input::Ime::*, Input::KeyboardAndMouse::*, WindowsAndMessaging::*},
+};
+
+const IMC_GETCONVERSIONMODE: usize = 0x0001;
+const IMC_GETOPENSTATUS: usize = 0x0005;
+
+// 通过目标窗口的默认 IME 窗口读取转换状态（可跨进程）
+pub struct ImmConversionState {
+    hwnd: HWND,
+}
+
+impl ImmConversionState {
+    pub fn new(hwnd: HWND) -> Self {
+        Self { hwnd }
+    }
+
+    unsafe fn query(&self, ime_wnd: HWND, command: usize) -> Option<usize> {
+        let mut result = 0usize;
+        let ok = SendMessageTimeoutW(
+            ime_wnd,
+            WM_IME_CONTROL,
+            WPARAM(command),
+            LPARAM(0),
+            SMTO_ABORTIFHUNG,
+            200,
+            Some(&mut result),
+        );
+        if ok.0 == 0 {
+            None
+        } else {
+            Some(result)
+        }
+    }
+}
+
+impl ConversionState for ImmConversionState {
+    fn is_chinese(&mut self) -> Option<bool> {
+        unsafe {
+            let ime_wnd = ImmGetDefaultIMEWnd(self.hwnd);
+            if ime_wnd.is_invalid() {
+                return None;
+            }
+            let open = self.query(ime_wnd, IMC_GETOPENSTATUS)?;
+            let conversion = self.query(ime_wnd, IMC_GETCONVERSIONMODE)? as u32;
+            Some(open != 0 && conversion & IME_CMODE_NATIVE.0 != 0)
+        }
+    }
+}
+
+// 通过 SendInput 向前台窗口发送按键
+pub struct SendInputInjector;
+
+impl InputInjector for SendInputInjector {
+    fn inject(&mut self, events: &[KeyEvent]) -> bool {
+        let inputs: Vec<INPUT> = events
+            .iter()
+            .map(|event| INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VIRTUAL_KEY(event.vk),
+                        wScan: 0,
+                        dwFlags: if event.key_up {
+                            KEYEVENTF_KEYUP
+                        } else {
+                            KEYBD_EVENT_FLAGS(0)
+                        },
+                        time: 0,
+                        dwExtraInfo: constants::INJECTED_INPUT_MARKER,
+                    },
+                },
+            })
+            .collect();
+
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            error!("SendInput 仅发送了 {}/{} 个按键事件", sent, inputs.len());
+            return false;
+        }
+        true
+    }
+}
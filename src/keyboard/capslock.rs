@@ -0,0 +1,303 @@
+use crate::config::CapsLockMode;
+use crate::ime::keystroke::KeyEvent;
+use crate::keyboard::tap_hold::{Decision, Effect, KeyInput};
+use crate::keyboard::ModifierState;
+use crate::utils::parse_hotkey::{MOD_LSHIFT, MOD_RSHIFT};
+
+pub const VK_CAPITAL: u32 = 0x14;
+
+// 绑定了 CapsLock 时的大小写锁定处理，与平台无关，由键盘钩子逐个输入按键事件。
+// - ShiftToggles：CapsLock 触发动作，Shift+CapsLock 照常切换大小写锁定
+// - LongPressToggles：轻按 CapsLock 触发动作，长按切换大小写锁定
+// - Native：CapsLock 作为普通热键注册，这里不做处理
+// 触发动作后若大小写锁定处于开启状态，模拟一次 CapsLock 将其关闭，避免卡在大写
+
+pub struct CapsLockMachine {
+    mode: CapsLockMode,
+    long_press_ms: u32,
+    id: Option<usize>, // 绑定的动作编号，未绑定时不处理
+    modifiers: ModifierState,
+    down_at: Option<u32>, // 被拦截的 CapsLock 按下时间
+    toggled: bool,        // 本次长按已切换过大小写锁定
+    passing: bool,        // 本次按键照常放行（如 Shift+CapsLock）
+}
+
+impl CapsLockMachine {
+    pub fn new(mode: CapsLockMode, long_press_ms: u32, id: Option<usize>) -> Self {
+        Self {
+            mode,
+            long_press_ms,
+            id,
+            modifiers: ModifierState::default(),
+            down_at: None,
+            toggled: false,
+            passing: false,
+        }
+    }
+
+    // caps_on 为当前大小写锁定是否开启
+    pub fn handle(&mut self, input: KeyInput, caps_on: bool) -> Decision {
+        if self.modifiers.update(input.vk, input.down) || input.vk != VK_CAPITAL {
+            return Decision::default();
+        }
+        let Some(id) = self.id.filter(|_| self.mode != CapsLockMode::Native) else {
+            return Decision::default();
+        };
+
+        if self.passing {
+            self.passing = input.down;
+            return Decision::default();
+        }
+
+        let mut effects = Vec::new();
+        match (self.down_at, input.down) {
+            // 首次按下
+            (None, true) => {
+                if self.mode == CapsLockMode::ShiftToggles {
+                    if self.modifiers.pressed() & (MOD_LSHIFT | MOD_RSHIFT) != 0 {
+                        self.passing = true;
+                        return Decision::default();
+                    }
+                    effects = fire(id, caps_on);
+                }
+                self.down_at = Some(input.time_ms);
+                self.toggled = false;
+            }
+            // 按住时的自动重复：长按达到时间后切换大小写锁定
+            (Some(down_at), true) => {
+                if self.mode == CapsLockMode::LongPressToggles
+                    && !self.toggled
+                    && input.time_ms.wrapping_sub(down_at) >= self.long_press_ms
+                {
+                    self.toggled = true;
+                    effects = toggle_caps();
+                }
+            }
+            (Some(down_at), false) => {
+                self.down_at = None;
+                if self.mode == CapsLockMode::LongPressToggles && !self.toggled {
+                    effects = if input.time_ms.wrapping_sub(down_at) >= self.long_press_ms {
+                        toggle_caps()
+                    } else {
+                        fire(id, caps_on)
+                    };
+                }
+            }
+            // 按下发生在绑定生效之前，松开照常放行
+            (None, false) => return Decision::default(),
+        }
+
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+}
+
+fn fire(id: usize, caps_on: bool) -> Vec<Effect> {
+    let mut effects = vec![Effect::Fire(id)];
+    if caps_on {
+        effects.extend(toggle_caps());
+    }
+    effects
+}
+
+// 模拟按下并松开 CapsLock
+fn toggle_caps() -> Vec<Effect> {
+    vec![
+        Effect::Inject(KeyEvent {
+            vk: VK_CAPITAL as u16,
+            key_up: false,
+        }),
+        Effect::Inject(KeyEvent {
+            vk: VK_CAPITAL as u16,
+            key_up: true,
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LSHIFT: u32 = 0xA0;
+    const ID: usize = 3;
+
+    fn input(vk: u32, down: bool, time_ms: u32) -> KeyInput {
+        KeyInput { vk, down, time_ms }
+    }
+
+    fn caps(down: bool, time_ms: u32) -> KeyInput {
+        input(VK_CAPITAL, down, time_ms)
+    }
+
+    fn swallowed(effects: Vec<Effect>) -> Decision {
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+
+    // 依次输入 (事件, 大小写锁定是否开启)
+    fn run(machine: &mut CapsLockMachine, inputs: &[(KeyInput, bool)]) -> Vec<Decision> {
+        inputs
+            .iter()
+            .map(|&(input, caps_on)| machine.handle(input, caps_on))
+            .collect()
+    }
+
+    #[test]
+    fn shift_toggles_fires_on_press() {
+        let mut machine = CapsLockMachine::new(CapsLockMode::ShiftToggles, 500, Some(ID));
+        let decisions = run(
+            &mut machine,
+            &[(caps(true, 0), false), (caps(false, 50), false)],
+        );
+        assert_eq!(
+            decisions,
+            vec![swallowed(vec![Effect::Fire(ID)]), swallowed(vec![])]
+        );
+    }
+
+    #[test]
+    fn shift_toggles_passes_shift_capslock() {
+        let mut machine = CapsLockMachine::new(CapsLockMode::ShiftToggles, 500, Some(ID));
+        let decisions = run(
+            &mut machine,
+            &[
+                (input(LSHIFT, true, 0), false),
+                (caps(true, 10), false),
+                (caps(true, 40), false),
+                (caps(false, 60), true),
+                (input(LSHIFT, false, 80), true),
+            ],
+        );
+        assert!(decisions
+            .iter()
+            .all(|decision| *decision == Decision::default()));
+
+        // 松开 Shift 后单独按 CapsLock 恢复触发动作
+        let decisions = run(&mut machine, &[(caps(true, 100), false)]);
+        assert_eq!(decisions, vec![swallowed(vec![Effect::Fire(ID)])]);
+    }
+
+    #[test]
+    fn auto_repeat_is_swallowed() {
+        for mode in [CapsLockMode::ShiftToggles, CapsLockMode::LongPressToggles] {
+            let mut machine = CapsLockMachine::new(mode, 500, Some(ID));
+            let decisions = run(
+                &mut machine,
+                &[
+                    (caps(true, 0), false),
+                    (caps(true, 30), false),
+                    (caps(true, 60), false),
+                    (caps(false, 90), false),
+                ],
+            );
+            assert!(
+                decisions.iter().all(|decision| decision.swallow),
+                "{:?}",
+                mode
+            );
+            let fired = decisions
+                .iter()
+                .flat_map(|decision| &decision.effects)
+                .filter(|effect| **effect == Effect::Fire(ID))
+                .count();
+            assert_eq!(fired, 1, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn long_press_toggles_once() {
+        let mut machine = CapsLockMachine::new(CapsLockMode::LongPressToggles, 500, Some(ID));
+        let decisions = run(
+            &mut machine,
+            &[
+                (caps(true, 0), false),
+                (caps(true, 300), false),
+                (caps(true, 500), false),
+                (caps(true, 700), true),
+                (caps(false, 900), true),
+            ],
+        );
+        assert_eq!(
+            decisions,
+            vec![
+                swallowed(vec![]),
+                swallowed(vec![]),
+                swallowed(toggle_caps()),
+                swallowed(vec![]),
+                swallowed(vec![]),
+            ]
+        );
+
+        // 没有自动重复时，松开时判断按住的时间
+        let decisions = run(
+            &mut machine,
+            &[(caps(true, 1000), false), (caps(false, 1600), false)],
+        );
+        assert_eq!(decisions, vec![swallowed(vec![]), swallowed(toggle_caps())]);
+    }
+
+    #[test]
+    fn long_press_toggles_fires_on_short_tap() {
+        let mut machine = CapsLockMachine::new(CapsLockMode::LongPressToggles, 500, Some(ID));
+        let decisions = run(
+            &mut machine,
+            &[(caps(true, 0), false), (caps(false, 100), false)],
+        );
+        assert_eq!(
+            decisions,
+            vec![swallowed(vec![]), swallowed(vec![Effect::Fire(ID)])]
+        );
+    }
+
+    #[test]
+    fn caps_lock_is_forced_off_after_switch() {
+        let mut machine = CapsLockMachine::new(CapsLockMode::ShiftToggles, 500, Some(ID));
+        let decisions = run(&mut machine, &[(caps(true, 0), true)]);
+        let mut expected = vec![Effect::Fire(ID)];
+        expected.extend(toggle_caps());
+        assert_eq!(decisions, vec![swallowed(expected.clone())]);
+
+        let mut machine = CapsLockMachine::new(CapsLockMode::LongPressToggles, 500, Some(ID));
+        let decisions = run(
+            &mut machine,
+            &[(caps(true, 0), true), (caps(false, 100), true)],
+        );
+        assert_eq!(decisions, vec![swallowed(vec![]), swallowed(expected)]);
+    }
+
+    #[test]
+    fn native_and_unbound_pass_through() {
+        for mut machine in [
+            CapsLockMachine::new(CapsLockMode::Native, 500, Some(ID)),
+            CapsLockMachine::new(CapsLockMode::ShiftToggles, 500, None),
+        ] {
+            let decisions = run(
+                &mut machine,
+                &[
+                    (caps(true, 0), true),
+                    (caps(true, 600), true),
+                    (caps(false, 700), true),
+                ],
+            );
+            assert!(decisions
+                .iter()
+                .all(|decision| *decision == Decision::default()));
+        }
+    }
+
+    #[test]
+    fn other_keys_pass_through() {
+        let mut machine = CapsLockMachine::new(CapsLockMode::ShiftToggles, 500, Some(ID));
+        let decisions = run(
+            &mut machine,
+            &[(input(0x41, true, 0), true), (input(0x41, false, 10), true)],
+        );
+        assert!(decisions
+            .iter()
+            .all(|decision| *decision == Decision::default()));
+    }
+}
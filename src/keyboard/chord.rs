@@ -0,0 +1,310 @@
+use crate::ime::keystroke::KeyEvent;
+use crate::keyboard::tap_hold::{Decision, Effect, KeyInput};
+use crate::keyboard::{menu_mask_effects, modifier_effects, ModifierState};
+use crate::utils::parse_hotkey::Hotkey;
+
+// 按键序列状态机，与平台无关，由键盘钩子逐个输入按键事件。
+// - 按下某个序列的前缀（如 Ctrl+K）时拦截该按键，进入等待状态
+// - 等待期间在超时时间内按下的下一段按键若匹配某个序列则触发动作并拦截
+// - 序列未完成（下一段按键不匹配，或超时后才按下其他键）时，先重放被拦截的前缀，
+//   再处理当前按键，以免前缀原有的功能（如编辑器中的 Ctrl+K 组合）失效
+// - 未处于等待状态时，除前缀外的按键一律放行
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ChordBinding {
+    pub prefix: Hotkey,
+    pub key: Hotkey,
+    pub id: usize, // 触发时返回的动作编号
+}
+
+struct Pending {
+    pressed: u32, // 按下前缀时的修饰键
+    vk: u32,      // 前缀按键本身，也用于识别按住时的自动重复
+    down_at: u32,
+}
+
+pub struct ChordMachine {
+    bindings: Vec<ChordBinding>,
+    timeout_ms: u32,
+    modifiers: ModifierState,
+    pending: Option<Pending>,
+    swallowed: Vec<u32>, // 已拦截按下事件、需要同时拦截松开事件的键
+}
+
+impl ChordMachine {
+    pub fn new(bindings: Vec<ChordBinding>, timeout_ms: u32) -> Self {
+        Self {
+            bindings,
+            timeout_ms,
+            modifiers: ModifierState::default(),
+            pending: None,
+            swallowed: Vec::new(),
+        }
+    }
+
+    pub fn handle(&mut self, input: KeyInput) -> Decision {
+        if self.modifiers.update(input.vk, input.down) {
+            return Decision::default();
+        }
+
+        if !input.down {
+            let swallow = match self.swallowed.iter().position(|&vk| vk == input.vk) {
+                Some(index) => {
+                    self.swallowed.remove(index);
+                    true
+                }
+                None => false,
+            };
+            return Decision {
+                swallow,
+                effects: Vec::new(),
+            };
+        }
+
+        let pressed = self.modifiers.pressed();
+
+        let mut effects = Vec::new();
+        if let Some(pending) = self.pending.take() {
+            // 按住前缀时的自动重复
+            if input.vk == pending.vk && self.swallowed.contains(&input.vk) {
+                self.pending = Some(pending);
+                return self.swallow(input.vk, Vec::new());
+            }
+            if input.time_ms.wrapping_sub(pending.down_at) <= self.timeout_ms {
+                let matched = self
+                    .bindings
+                    .iter()
+                    .find(|b| {
+                        b.prefix.matches(pending.pressed, pending.vk)
+                            && b.key.matches(pressed, input.vk)
+                    })
+                    .map(|b| b.id);
+                if let Some(id) = matched {
+                    let mut effects = menu_mask_effects(pressed);
+                    effects.push(Effect::Fire(id));
+                    return self.swallow(input.vk, effects);
+                }
+            }
+            effects = replay(&pending, pressed);
+        }
+
+        if self
+            .bindings
+            .iter()
+            .any(|b| b.prefix.matches(pressed, input.vk))
+        {
+            self.pending = Some(Pending {
+                pressed,
+                vk: input.vk,
+                down_at: input.time_ms,
+            });
+            effects.extend(menu_mask_effects(pressed));
+            return self.swallow(input.vk, effects);
+        }
+
+        if effects.is_empty() {
+            return Decision::default();
+        }
+        // 拦截当前按键并在重放的前缀之后重新模拟，以保证顺序；其松开事件照常放行
+        effects.push(key_event(input.vk, false));
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+
+    fn swallow(&mut self, vk: u32, effects: Vec<Effect>) -> Decision {
+        if !self.swallowed.contains(&vk) {
+            self.swallowed.push(vk);
+        }
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+}
+
+// 重放被拦截的前缀：补按当时按下、现在已松开的修饰键，并暂时松开当时没有按下的修饰键
+fn replay(pending: &Pending, pressed: u32) -> Vec<Effect> {
+    let missing = pending.pressed & !pressed;
+    let extra = pressed & !pending.pressed;
+    let mut effects = modifier_effects(extra, true);
+    effects.extend(modifier_effects(missing, false));
+    effects.push(key_event(pending.vk, false));
+    effects.push(key_event(pending.vk, true));
+    effects.extend(modifier_effects(missing, true));
+    effects.extend(modifier_effects(extra, false));
+    effects
+}
+
+fn key_event(vk: u32, key_up: bool) -> Effect {
+    Effect::Inject(KeyEvent {
+        vk: vk as u16,
+        key_up,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LCTRL: u32 = 0xA2;
+    const LSHIFT: u32 = 0xA0;
+    const K: u32 = 0x4B;
+    const E: u32 = 0x45;
+    const X: u32 = 0x58;
+
+    fn machine() -> ChordMachine {
+        ChordMachine::new(
+            vec![ChordBinding {
+                prefix: "Ctrl+K".parse().unwrap(),
+                key: "E".parse().unwrap(),
+                id: 3,
+            }],
+            1000,
+        )
+    }
+
+    fn down(machine: &mut ChordMachine, vk: u32, time_ms: u32) -> Decision {
+        machine.handle(KeyInput {
+            vk,
+            down: true,
+            time_ms,
+        })
+    }
+
+    fn up(machine: &mut ChordMachine, vk: u32, time_ms: u32) -> Decision {
+        machine.handle(KeyInput {
+            vk,
+            down: false,
+            time_ms,
+        })
+    }
+
+    fn swallowed(effects: Vec<Effect>) -> Decision {
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+
+    // 按下 Ctrl+K 后松开 K，Ctrl 仍按住
+    fn press_prefix(machine: &mut ChordMachine, at: u32) {
+        assert_eq!(down(machine, LCTRL, at), Decision::default());
+        assert_eq!(down(machine, K, at + 10), swallowed(Vec::new()));
+        assert_eq!(up(machine, K, at + 50), swallowed(Vec::new()));
+    }
+
+    #[test]
+    fn second_stroke_fires() {
+        let mut machine = machine();
+        press_prefix(&mut machine, 0);
+        assert_eq!(up(&mut machine, LCTRL, 100), Decision::default());
+        assert_eq!(down(&mut machine, E, 200), swallowed(vec![Effect::Fire(3)]));
+        // 配对的松开事件同样拦截，之后的 E 照常输入
+        assert_eq!(up(&mut machine, E, 250), swallowed(Vec::new()));
+        assert_eq!(down(&mut machine, E, 300), Decision::default());
+        assert_eq!(up(&mut machine, E, 350), Decision::default());
+    }
+
+    #[test]
+    fn mismatch_replays_prefix_before_current_key() {
+        let mut machine = machine();
+        press_prefix(&mut machine, 0);
+        assert_eq!(
+            down(&mut machine, X, 200),
+            swallowed(vec![
+                key_event(K, false),
+                key_event(K, true),
+                key_event(X, false),
+            ])
+        );
+        // 重新模拟的只有按下事件，松开事件照常放行
+        assert_eq!(up(&mut machine, X, 250), Decision::default());
+        assert_eq!(down(&mut machine, E, 300), Decision::default());
+    }
+
+    #[test]
+    fn replay_restores_modifiers_of_the_prefix() {
+        let mut machine = machine();
+        press_prefix(&mut machine, 0);
+        assert_eq!(up(&mut machine, LCTRL, 100), Decision::default());
+        assert_eq!(down(&mut machine, LSHIFT, 150), Decision::default());
+        assert_eq!(
+            down(&mut machine, X, 200),
+            swallowed(vec![
+                key_event(LSHIFT, true),
+                key_event(LCTRL, false),
+                key_event(K, false),
+                key_event(K, true),
+                key_event(LCTRL, true),
+                key_event(LSHIFT, false),
+                key_event(X, false),
+            ])
+        );
+    }
+
+    #[test]
+    fn timeout_replays_prefix_on_next_key() {
+        let mut machine = machine();
+        press_prefix(&mut machine, 0);
+        assert_eq!(up(&mut machine, LCTRL, 100), Decision::default());
+        // 超时后按下的 E 不再触发
+        assert_eq!(
+            down(&mut machine, E, 1500),
+            swallowed(vec![
+                key_event(LCTRL, false),
+                key_event(K, false),
+                key_event(K, true),
+                key_event(LCTRL, true),
+                key_event(E, false),
+            ])
+        );
+        assert_eq!(up(&mut machine, E, 1550), Decision::default());
+    }
+
+    #[test]
+    fn timed_out_prefix_pressed_again_starts_a_new_sequence() {
+        let mut machine = machine();
+        press_prefix(&mut machine, 0);
+        assert_eq!(
+            down(&mut machine, K, 1500),
+            swallowed(vec![key_event(K, false), key_event(K, true)])
+        );
+        assert_eq!(up(&mut machine, K, 1550), swallowed(Vec::new()));
+        assert_eq!(up(&mut machine, LCTRL, 1560), Decision::default());
+        assert_eq!(
+            down(&mut machine, E, 1600),
+            swallowed(vec![Effect::Fire(3)])
+        );
+    }
+
+    #[test]
+    fn auto_repeat_of_held_prefix_is_swallowed() {
+        let mut machine = machine();
+        assert_eq!(down(&mut machine, LCTRL, 0), Decision::default());
+        assert_eq!(down(&mut machine, K, 10), swallowed(Vec::new()));
+        // 按住超过超时时间的自动重复也不重放前缀
+        for time_ms in [500, 530, 1500] {
+            assert_eq!(down(&mut machine, K, time_ms), swallowed(Vec::new()));
+        }
+        assert_eq!(up(&mut machine, K, 1600), swallowed(Vec::new()));
+        assert_eq!(
+            down(&mut machine, X, 1700),
+            swallowed(vec![
+                key_event(K, false),
+                key_event(K, true),
+                key_event(X, false),
+            ])
+        );
+    }
+
+    #[test]
+    fn keys_without_pending_sequence_pass() {
+        let mut machine = machine();
+        assert_eq!(down(&mut machine, K, 0), Decision::default());
+        assert_eq!(up(&mut machine, K, 50), Decision::default());
+        assert_eq!(down(&mut machine, E, 100), Decision::default());
+        assert_eq!(up(&mut machine, E, 150), Decision::default());
+    }
+}
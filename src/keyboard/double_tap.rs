@@ -0,0 +1,211 @@
+use crate::keyboard::key_matches;
+use crate::keyboard::tap_hold::KeyInput;
+
+// 双击状态机，与平台无关，由键盘钩子逐个输入按键事件。
+// - 一次轻按：绑定的键按下后在轻按超时时间内松开，且期间没有按其他键
+// - 第一次轻按松开后，在双击间隔内再次轻按同一个键，触发动作
+// - 两次轻按之间按下任何其他键（如 Ctrl+C）都会打断双击，需要重新开始
+// - 不拦截任何按键，单次按下时保持原有功能
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DoubleTapBinding {
+    pub key: u32,
+    pub id: usize, // 触发时返回的动作编号
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+    Idle,
+    FirstDown { index: usize, down_at: u32 },
+    Released { index: usize, up_at: u32 },
+    SecondDown { index: usize, down_at: u32 },
+}
+
+pub struct DoubleTapMachine {
+    bindings: Vec<DoubleTapBinding>,
+    tap_timeout_ms: u32,
+    interval_ms: u32,
+    phase: Phase,
+}
+
+impl DoubleTapMachine {
+    pub fn new(bindings: Vec<DoubleTapBinding>, tap_timeout_ms: u32, interval_ms: u32) -> Self {
+        Self {
+            bindings,
+            tap_timeout_ms,
+            interval_ms,
+            phase: Phase::Idle,
+        }
+    }
+
+    // 处理一个按键事件，完成双击时返回动作编号
+    pub fn handle(&mut self, input: KeyInput) -> Option<usize> {
+        if input.down {
+            self.key_down(input);
+            None
+        } else {
+            self.key_up(input)
+        }
+    }
+
+    fn key_down(&mut self, input: KeyInput) {
+        let Some(index) = self
+            .bindings
+            .iter()
+            .position(|b| key_matches(b.key, input.vk))
+        else {
+            // 其他键打断双击
+            self.phase = Phase::Idle;
+            return;
+        };
+
+        self.phase = match self.phase {
+            // 按住时的自动重复
+            Phase::FirstDown { index: i, .. } | Phase::SecondDown { index: i, .. }
+                if i == index =>
+            {
+                return;
+            }
+            Phase::Released { index: i, up_at }
+                if i == index && input.time_ms.wrapping_sub(up_at) <= self.interval_ms =>
+            {
+                Phase::SecondDown {
+                    index,
+                    down_at: input.time_ms,
+                }
+            }
+            _ => Phase::FirstDown {
+                index,
+                down_at: input.time_ms,
+            },
+        };
+    }
+
+    fn key_up(&mut self, input: KeyInput) -> Option<usize> {
+        let (index, down_at, second) = match self.phase {
+            Phase::FirstDown { index, down_at } => (index, down_at, false),
+            Phase::SecondDown { index, down_at } => (index, down_at, true),
+            // 松开之前按下的其他键不影响双击
+            _ => return None,
+        };
+        if !key_matches(self.bindings[index].key, input.vk) {
+            return None;
+        }
+
+        // 按住时间过长不算轻按
+        if input.time_ms.wrapping_sub(down_at) > self.tap_timeout_ms {
+            self.phase = Phase::Idle;
+            return None;
+        }
+
+        if second {
+            self.phase = Phase::Idle;
+            Some(self.bindings[index].id)
+        } else {
+            self.phase = Phase::Released {
+                index,
+                up_at: input.time_ms,
+            };
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: u32 = 0x11;
+    const LCTRL: u32 = 0xA2;
+    const RCTRL: u32 = 0xA3;
+    const C: u32 = 0x43;
+
+    // 按键事件：(键码, 是否按下, 时间)
+    fn run(binding_key: u32, events: &[(u32, bool, u32)]) -> Vec<usize> {
+        let mut machine = DoubleTapMachine::new(
+            vec![DoubleTapBinding {
+                key: binding_key,
+                id: 7,
+            }],
+            200,
+            300,
+        );
+        events
+            .iter()
+            .filter_map(|&(vk, down, time_ms)| machine.handle(KeyInput { vk, down, time_ms }))
+            .collect()
+    }
+
+    fn tap(vk: u32, at: u32) -> [(u32, bool, u32); 2] {
+        [(vk, true, at), (vk, false, at + 50)]
+    }
+
+    #[test]
+    fn double_tap_within_interval_fires() {
+        let events = [tap(LCTRL, 0), tap(LCTRL, 200)].concat();
+        assert_eq!(run(CTRL, &events), vec![7]);
+    }
+
+    #[test]
+    fn combination_between_taps_does_not_fire() {
+        // Ctrl，然后 Ctrl+C，然后 Ctrl
+        let events = [
+            tap(LCTRL, 0).to_vec(),
+            vec![
+                (LCTRL, true, 100),
+                (C, true, 120),
+                (C, false, 140),
+                (LCTRL, false, 160),
+            ],
+            tap(LCTRL, 200).to_vec(),
+        ]
+        .concat();
+        assert!(run(CTRL, &events).is_empty());
+    }
+
+    #[test]
+    fn second_tap_after_interval_does_not_fire() {
+        let events = [tap(LCTRL, 0), tap(LCTRL, 351)].concat();
+        assert!(run(CTRL, &events).is_empty());
+
+        // 超时的第二次轻按作为新的第一次
+        let events = [tap(LCTRL, 0), tap(LCTRL, 351), tap(LCTRL, 500)].concat();
+        assert_eq!(run(CTRL, &events), vec![7]);
+    }
+
+    #[test]
+    fn long_press_is_not_a_tap() {
+        let events = [
+            tap(LCTRL, 0).to_vec(),
+            vec![(LCTRL, true, 100), (LCTRL, true, 130), (LCTRL, false, 400)],
+        ]
+        .concat();
+        assert!(run(CTRL, &events).is_empty());
+    }
+
+    #[test]
+    fn auto_repeat_is_ignored() {
+        let events = [
+            vec![(LCTRL, true, 0), (LCTRL, true, 30), (LCTRL, false, 60)],
+            tap(LCTRL, 100).to_vec(),
+        ]
+        .concat();
+        assert_eq!(run(CTRL, &events), vec![7]);
+    }
+
+    // 不区分左右的绑定同时匹配左右两侧，因此先按左 Ctrl 再按右 Ctrl 也算双击
+    #[test]
+    fn generic_binding_accepts_mixed_sides() {
+        let events = [tap(LCTRL, 0), tap(RCTRL, 200)].concat();
+        assert_eq!(run(CTRL, &events), vec![7]);
+    }
+
+    #[test]
+    fn side_specific_binding_requires_same_side() {
+        let events = [tap(LCTRL, 0), tap(RCTRL, 200)].concat();
+        assert!(run(LCTRL, &events).is_empty());
+
+        let events = [tap(LCTRL, 0), tap(LCTRL, 200)].concat();
+        assert_eq!(run(LCTRL, &events), vec![7]);
+    }
+}
@@ -0,0 +1,103 @@
+pub mod capslock;
+pub mod chord;
+pub mod double_tap;
+pub mod side_hotkey;
+pub mod tap_hold;
+
+use crate::ime::keystroke::KeyEvent;
+use crate::keyboard::tap_hold::Effect;
+use crate::utils::parse_hotkey::{
+    MOD_LALT, MOD_LCONTROL, MOD_LSHIFT, MOD_LWIN, MOD_RALT, MOD_RCONTROL, MOD_RSHIFT, MOD_RWIN,
+};
+
+// 绑定的键是否与实际按下的键匹配：钩子报告的是区分左右的键码，
+// 不区分左右的 Shift/Ctrl/Alt 同时匹配左右两侧
+pub fn key_matches(binding: u32, vk: u32) -> bool {
+    match binding {
+        0x10 => matches!(vk, 0x10 | 0xA0 | 0xA1),
+        0x11 => matches!(vk, 0x11 | 0xA2 | 0xA3),
+        0x12 => matches!(vk, 0x12 | 0xA4 | 0xA5),
+        _ => binding == vk,
+    }
+}
+
+// 修饰键的虚拟键码对应的 MOD_L*/MOD_R* 位；钩子偶尔报告的不区分左右的键码归到左侧
+pub fn modifier_bit(vk: u32) -> Option<u32> {
+    match vk {
+        0x10 | 0xA0 => Some(MOD_LSHIFT),
+        0xA1 => Some(MOD_RSHIFT),
+        0x11 | 0xA2 => Some(MOD_LCONTROL),
+        0xA3 => Some(MOD_RCONTROL),
+        0x12 | 0xA4 => Some(MOD_LALT),
+        0xA5 => Some(MOD_RALT),
+        0x5B => Some(MOD_LWIN),
+        0x5C => Some(MOD_RWIN),
+        _ => None,
+    }
+}
+
+// MOD_L*/MOD_R* 位对应的区分左右的虚拟键码
+const MODIFIER_KEYS: [(u32, u16); 8] = [
+    (MOD_LSHIFT, 0xA0),
+    (MOD_RSHIFT, 0xA1),
+    (MOD_LCONTROL, 0xA2),
+    (MOD_RCONTROL, 0xA3),
+    (MOD_LALT, 0xA4),
+    (MOD_RALT, 0xA5),
+    (MOD_LWIN, 0x5B),
+    (MOD_RWIN, 0x5C),
+];
+
+// 模拟按下或松开 bits 中的修饰键
+pub fn modifier_effects(bits: u32, key_up: bool) -> Vec<Effect> {
+    MODIFIER_KEYS
+        .iter()
+        .filter(|&&(bit, _)| bits & bit != 0)
+        .map(|&(_, vk)| Effect::Inject(KeyEvent { vk, key_up }))
+        .collect()
+}
+
+// 根据钩子收到的按键事件记录当前按下的修饰键
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ModifierState {
+    pressed: u32,
+}
+
+impl ModifierState {
+    // 更新状态，返回该键是否为修饰键
+    pub fn update(&mut self, vk: u32, down: bool) -> bool {
+        let Some(bit) = modifier_bit(vk) else {
+            return false;
+        };
+        if down {
+            self.pressed |= bit;
+        } else {
+            self.pressed &= !bit;
+        }
+        true
+    }
+
+    // 当前按下的修饰键，只含 MOD_L*/MOD_R* 位
+    pub fn pressed(&self) -> u32 {
+        self.pressed
+    }
+}
+
+// 拦截了 Alt 或 Win 组合键后单独松开 Alt/Win 会激活菜单栏或开始菜单，
+// 触发前先模拟一次未分配的按键 (0xE8) 作为遮罩
+pub fn menu_mask_effects(pressed: u32) -> Vec<Effect> {
+    const MENU_MASK_KEY: u16 = 0xE8;
+    if pressed & (MOD_LALT | MOD_RALT | MOD_LWIN | MOD_RWIN) == 0 {
+        return Vec::new();
+    }
+    vec![
+        Effect::Inject(KeyEvent {
+            vk: MENU_MASK_KEY,
+            key_up: false,
+        }),
+        Effect::Inject(KeyEvent {
+            vk: MENU_MASK_KEY,
+            key_up: true,
+        }),
+    ]
+}
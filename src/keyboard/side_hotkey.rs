@@ -0,0 +1,179 @@
+use crate::keyboard::tap_hold::{Decision, Effect, KeyInput};
+use crate::keyboard::{menu_mask_effects, ModifierState};
+use crate::utils::parse_hotkey::Hotkey;
+
+// 区分左右修饰键的热键（如 "RAlt+Space"），与平台无关，由键盘钩子逐个输入按键事件。
+// 按下匹配的组合键时触发动作并拦截该键的按下与松开，按住时的自动重复不再触发
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SideHotkeyBinding {
+    pub hotkey: Hotkey,
+    pub id: usize, // 触发时返回的动作编号
+}
+
+pub struct SideHotkeyMachine {
+    bindings: Vec<SideHotkeyBinding>,
+    modifiers: ModifierState,
+    swallowed: Vec<u32>, // 已拦截按下事件、需要同时拦截松开事件的键
+}
+
+impl SideHotkeyMachine {
+    pub fn new(bindings: Vec<SideHotkeyBinding>) -> Self {
+        Self {
+            bindings,
+            modifiers: ModifierState::default(),
+            swallowed: Vec::new(),
+        }
+    }
+
+    pub fn handle(&mut self, input: KeyInput) -> Decision {
+        if self.modifiers.update(input.vk, input.down) {
+            return Decision::default();
+        }
+
+        if !input.down {
+            let before = self.swallowed.len();
+            self.swallowed.retain(|&vk| vk != input.vk);
+            return Decision {
+                swallow: self.swallowed.len() != before,
+                effects: Vec::new(),
+            };
+        }
+
+        // 按住时的自动重复
+        if self.swallowed.contains(&input.vk) {
+            return Decision {
+                swallow: true,
+                effects: Vec::new(),
+            };
+        }
+
+        let pressed = self.modifiers.pressed();
+        let Some(binding) = self
+            .bindings
+            .iter()
+            .find(|b| b.hotkey.matches(pressed, input.vk))
+        else {
+            return Decision::default();
+        };
+
+        self.swallowed.push(input.vk);
+        let mut effects = menu_mask_effects(pressed);
+        effects.push(Effect::Fire(binding.id));
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::tap_hold::{TapBinding, TapHoldMachine};
+    use crate::utils::parse_hotkey::MOD_RALT;
+    use crate::utils::trigger::Trigger;
+
+    const LALT: u32 = 0xA4;
+    const RALT: u32 = 0xA5;
+    const RCTRL: u32 = 0xA3;
+    const SPACE: u32 = 0x20;
+
+    fn machine() -> SideHotkeyMachine {
+        SideHotkeyMachine::new(vec![SideHotkeyBinding {
+            hotkey: "RAlt+Space".parse().unwrap(),
+            id: 4,
+        }])
+    }
+
+    fn handle(machine: &mut SideHotkeyMachine, vk: u32, down: bool) -> Decision {
+        machine.handle(KeyInput {
+            vk,
+            down,
+            time_ms: 0,
+        })
+    }
+
+    fn fired(decision: &Decision) -> bool {
+        decision.effects.contains(&Effect::Fire(4))
+    }
+
+    #[test]
+    fn right_alt_fires_and_swallows_the_pair() {
+        let mut machine = machine();
+        assert_eq!(handle(&mut machine, RALT, true), Decision::default());
+        let decision = handle(&mut machine, SPACE, true);
+        assert!(decision.swallow);
+        // 拦截了 Alt 组合键，先模拟遮罩按键再触发
+        let mut expected = menu_mask_effects(MOD_RALT);
+        expected.push(Effect::Fire(4));
+        assert_eq!(decision.effects, expected);
+        assert!(handle(&mut machine, SPACE, false).swallow);
+        assert_eq!(handle(&mut machine, RALT, false), Decision::default());
+        // 松开后单独的 Space 照常输入
+        assert_eq!(handle(&mut machine, SPACE, true), Decision::default());
+        assert_eq!(handle(&mut machine, SPACE, false), Decision::default());
+    }
+
+    #[test]
+    fn left_alt_does_not_fire() {
+        let mut machine = machine();
+        handle(&mut machine, LALT, true);
+        assert_eq!(handle(&mut machine, SPACE, true), Decision::default());
+        assert_eq!(handle(&mut machine, SPACE, false), Decision::default());
+        handle(&mut machine, LALT, false);
+    }
+
+    #[test]
+    fn auto_repeat_does_not_fire_again() {
+        let mut machine = machine();
+        handle(&mut machine, RALT, true);
+        assert!(fired(&handle(&mut machine, SPACE, true)));
+        for _ in 0..3 {
+            let decision = handle(&mut machine, SPACE, true);
+            assert!(decision.swallow);
+            assert!(decision.effects.is_empty());
+        }
+        assert!(handle(&mut machine, SPACE, false).swallow);
+        // 松开后再次按下重新触发
+        assert!(fired(&handle(&mut machine, SPACE, true)));
+    }
+
+    #[test]
+    fn bare_right_ctrl_is_a_tap() {
+        // 单独的修饰键解析为轻按，由轻按状态机处理，这里照常放行
+        assert_eq!(
+            "RCtrl".parse::<Trigger>(),
+            Ok(Trigger::Tap {
+                key: RCTRL,
+                hold: None
+            })
+        );
+        let mut machine = machine();
+        assert_eq!(handle(&mut machine, RCTRL, true), Decision::default());
+        assert_eq!(handle(&mut machine, RCTRL, false), Decision::default());
+
+        let mut taps = TapHoldMachine::new(
+            vec![TapBinding {
+                key: RCTRL,
+                hold: None,
+                id: 5,
+            }],
+            200,
+        );
+        let tap = |taps: &mut TapHoldMachine, vk: u32| {
+            taps.handle(KeyInput {
+                vk,
+                down: true,
+                time_ms: 0,
+            });
+            taps.handle(KeyInput {
+                vk,
+                down: false,
+                time_ms: 50,
+            })
+        };
+        assert_eq!(tap(&mut taps, RCTRL).effects, vec![Effect::Fire(5)]);
+        assert!(tap(&mut taps, 0xA2).effects.is_empty());
+    }
+}
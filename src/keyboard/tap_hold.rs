@@ -0,0 +1,339 @@
+use crate::ime::keystroke::KeyEvent;
+use crate::keyboard::key_matches;
+
+// 轻按/按住状态机，与平台无关，由键盘钩子逐个输入按键事件。
+// - 绑定的键按下后在超时时间内松开，且期间没有按其他键，视为轻按，触发动作
+// - 未设置 hold 的绑定不拦截按键，按住时保持原有功能（如 Shift）
+// - 设置了 hold 的绑定会拦截该键；按住超时或期间按下其他键时，改为模拟按下 hold 键
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyInput {
+    pub vk: u32,
+    pub down: bool,
+    pub time_ms: u32, // 事件时间戳（毫秒，允许回绕）
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TapBinding {
+    pub key: u32,
+    pub hold: Option<u32>,
+    pub id: usize, // 触发时返回的动作编号
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Effect {
+    Fire(usize),
+    Inject(KeyEvent),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Decision {
+    pub swallow: bool, // 是否拦截当前事件
+    pub effects: Vec<Effect>,
+}
+
+impl Decision {
+    fn pass() -> Self {
+        Self::default()
+    }
+}
+
+struct Pending {
+    binding: TapBinding,
+    down_at: u32,
+    interrupted: bool, // 按住期间按下了其他键
+    holding: bool,     // 已模拟按下 hold 键
+}
+
+pub struct TapHoldMachine {
+    bindings: Vec<TapBinding>,
+    timeout_ms: u32,
+    pending: Option<Pending>,
+}
+
+impl TapHoldMachine {
+    pub fn new(bindings: Vec<TapBinding>, timeout_ms: u32) -> Self {
+        Self {
+            bindings,
+            timeout_ms,
+            pending: None,
+        }
+    }
+
+    pub fn handle(&mut self, input: KeyInput) -> Decision {
+        match self.pending.as_mut() {
+            Some(pending) if key_matches(pending.binding.key, input.vk) => {
+                if input.down {
+                    Self::repeat(pending, input, self.timeout_ms)
+                } else {
+                    let pending = self.pending.take().unwrap();
+                    Self::release(pending, input, self.timeout_ms)
+                }
+            }
+            Some(pending) => {
+                if input.down {
+                    Self::interrupt(pending, input)
+                } else {
+                    Decision::pass()
+                }
+            }
+            None => {
+                if !input.down {
+                    return Decision::pass();
+                }
+                let Some(binding) = self.bindings.iter().find(|b| key_matches(b.key, input.vk))
+                else {
+                    return Decision::pass();
+                };
+                self.pending = Some(Pending {
+                    binding: *binding,
+                    down_at: input.time_ms,
+                    interrupted: false,
+                    holding: false,
+                });
+                Decision {
+                    swallow: binding.hold.is_some(),
+                    effects: Vec::new(),
+                }
+            }
+        }
+    }
+
+    // 按住时的自动重复：超时后开始模拟 hold 键
+    fn repeat(pending: &mut Pending, input: KeyInput, timeout_ms: u32) -> Decision {
+        let Some(hold) = pending.binding.hold else {
+            return Decision::pass();
+        };
+        let mut effects = Vec::new();
+        if !pending.holding && input.time_ms.wrapping_sub(pending.down_at) > timeout_ms {
+            pending.holding = true;
+            effects.push(Effect::Inject(key_event(hold, false)));
+        }
+        Decision {
+            swallow: true,
+            effects,
+        }
+    }
+
+    fn release(pending: Pending, input: KeyInput, timeout_ms: u32) -> Decision {
+        let mut effects = Vec::new();
+        if pending.holding {
+            if let Some(hold) = pending.binding.hold {
+                effects.push(Effect::Inject(key_event(hold, true)));
+            }
+        } else if !pending.interrupted && input.time_ms.wrapping_sub(pending.down_at) <= timeout_ms
+        {
+            effects.push(Effect::Fire(pending.binding.id));
+        }
+        Decision {
+            swallow: pending.binding.hold.is_some(),
+            effects,
+        }
+    }
+
+    // 按住期间按下其他键：不再视为轻按；有 hold 键时先模拟按下 hold 键，再重放当前按键以保证顺序
+    fn interrupt(pending: &mut Pending, input: KeyInput) -> Decision {
+        pending.interrupted = true;
+        match pending.binding.hold {
+            Some(hold) if !pending.holding => {
+                pending.holding = true;
+                Decision {
+                    swallow: true,
+                    effects: vec![
+                        Effect::Inject(key_event(hold, false)),
+                        Effect::Inject(key_event(input.vk, false)),
+                    ],
+                }
+            }
+            _ => Decision::pass(),
+        }
+    }
+}
+
+fn key_event(vk: u32, key_up: bool) -> KeyEvent {
+    KeyEvent {
+        vk: vk as u16,
+        key_up,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHIFT: u32 = 0x10;
+    const LSHIFT: u32 = 0xA0;
+    const LCTRL: u32 = 0xA2;
+    const CAPSLOCK: u32 = 0x14;
+    const A: u32 = 0x41;
+    const C: u32 = 0x43;
+
+    fn down(vk: u32, time_ms: u32) -> KeyInput {
+        KeyInput {
+            vk,
+            down: true,
+            time_ms,
+        }
+    }
+
+    fn up(vk: u32, time_ms: u32) -> KeyInput {
+        KeyInput {
+            vk,
+            down: false,
+            time_ms,
+        }
+    }
+
+    fn inject(vk: u32, key_up: bool) -> Effect {
+        Effect::Inject(key_event(vk, key_up))
+    }
+
+    fn machine() -> TapHoldMachine {
+        TapHoldMachine::new(
+            vec![
+                TapBinding {
+                    key: SHIFT,
+                    hold: None,
+                    id: 1,
+                },
+                TapBinding {
+                    key: CAPSLOCK,
+                    hold: Some(LCTRL),
+                    id: 2,
+                },
+            ],
+            200,
+        )
+    }
+
+    // 依次输入按键事件，返回每个事件的处理结果
+    fn feed(machine: &mut TapHoldMachine, inputs: &[KeyInput]) -> Vec<Decision> {
+        inputs.iter().map(|&input| machine.handle(input)).collect()
+    }
+
+    fn fired(decisions: &[Decision]) -> Vec<usize> {
+        decisions
+            .iter()
+            .flat_map(|decision| &decision.effects)
+            .filter_map(|effect| match effect {
+                Effect::Fire(id) => Some(*id),
+                Effect::Inject(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lone_tap_fires() {
+        let decisions = feed(&mut machine(), &[down(LSHIFT, 0), up(LSHIFT, 100)]);
+        // 未设置 hold 的键不拦截
+        assert!(decisions.iter().all(|decision| !decision.swallow));
+        assert_eq!(fired(&decisions), vec![1]);
+    }
+
+    #[test]
+    fn auto_repeat_within_timeout_still_taps() {
+        let decisions = feed(
+            &mut machine(),
+            &[
+                down(LSHIFT, 0),
+                down(LSHIFT, 30),
+                down(LSHIFT, 60),
+                up(LSHIFT, 90),
+            ],
+        );
+        assert_eq!(fired(&decisions), vec![1]);
+    }
+
+    #[test]
+    fn combination_cancels_tap() {
+        let decisions = feed(
+            &mut machine(),
+            &[down(LSHIFT, 0), down(A, 20), up(A, 40), up(LSHIFT, 60)],
+        );
+        assert!(decisions.iter().all(|decision| !decision.swallow));
+        assert!(fired(&decisions).is_empty());
+    }
+
+    #[test]
+    fn release_after_timeout_does_not_fire() {
+        let decisions = feed(&mut machine(), &[down(LSHIFT, 0), up(LSHIFT, 201)]);
+        assert!(fired(&decisions).is_empty());
+
+        let decisions = feed(&mut machine(), &[down(CAPSLOCK, 0), up(CAPSLOCK, 500)]);
+        assert!(decisions.iter().all(|decision| decision.swallow));
+        assert!(decisions.iter().all(|decision| decision.effects.is_empty()));
+    }
+
+    #[test]
+    fn timestamps_may_wrap() {
+        let decisions = feed(
+            &mut machine(),
+            &[down(LSHIFT, u32::MAX - 50), up(LSHIFT, 49)],
+        );
+        assert_eq!(fired(&decisions), vec![1]);
+    }
+
+    #[test]
+    fn tap_with_hold_key_fires_and_swallows() {
+        let decisions = feed(&mut machine(), &[down(CAPSLOCK, 0), up(CAPSLOCK, 80)]);
+        assert!(decisions.iter().all(|decision| decision.swallow));
+        assert_eq!(fired(&decisions), vec![2]);
+    }
+
+    #[test]
+    fn foreign_key_before_timeout_presses_hold_key_first() {
+        let decisions = feed(
+            &mut machine(),
+            &[down(CAPSLOCK, 0), down(C, 50), up(C, 80), up(CAPSLOCK, 100)],
+        );
+        assert_eq!(
+            decisions,
+            vec![
+                Decision {
+                    swallow: true,
+                    effects: vec![],
+                },
+                // 先按下 LCtrl，再重放 C，目标程序收到 Ctrl+C
+                Decision {
+                    swallow: true,
+                    effects: vec![inject(LCTRL, false), inject(C, false)],
+                },
+                Decision::pass(),
+                Decision {
+                    swallow: true,
+                    effects: vec![inject(LCTRL, true)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn holding_past_timeout_presses_hold_key() {
+        let decisions = feed(
+            &mut machine(),
+            &[
+                down(CAPSLOCK, 0),
+                down(CAPSLOCK, 150),
+                down(CAPSLOCK, 250),
+                down(CAPSLOCK, 300),
+                down(C, 320),
+                up(C, 340),
+                up(CAPSLOCK, 400),
+            ],
+        );
+        let effects: Vec<Vec<Effect>> = decisions.into_iter().map(|d| d.effects).collect();
+        assert_eq!(
+            effects,
+            vec![
+                vec![],
+                vec![],
+                vec![inject(LCTRL, false)],
+                vec![],
+                // 已按下 LCtrl，其他键照常通过
+                vec![],
+                vec![],
+                vec![inject(LCTRL, true)],
+            ]
+        );
+    }
+}
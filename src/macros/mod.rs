@@ -0,0 +1,288 @@
+use crate::config::{Action, ImeMode, MacroCondition, MacroStep};
+use log::{info, warn};
+
+// 宏的执行，与平台无关，通过 MacroBackend 读取状态并执行动作。
+// - 步骤按顺序执行，带 delay_ms 的步骤先返回等待时间，由调用方在等待结束后继续执行
+// - 条件在等待结束、执行该步骤时判断，因此可以依赖前面步骤的结果
+// - 宏中不能再调用宏，这样的步骤直接跳过
+// - 某一步执行失败时中止整个宏，后续步骤可能依赖该步骤的结果
+
+pub trait MacroBackend {
+    fn ime_mode(&self) -> ImeMode;
+    fn master_switch(&self) -> bool;
+    fn foreground_app(&self) -> Option<String>;
+    fn execute(&mut self, action: &Action) -> Result<(), String>;
+}
+
+impl MacroCondition {
+    pub fn holds(&self, backend: &impl MacroBackend) -> bool {
+        match self {
+            MacroCondition::Mode(mode) => backend.ime_mode() == *mode,
+            MacroCondition::MasterSwitch(on) => backend.master_switch() == *on,
+            MacroCondition::App(app) => backend
+                .foreground_app()
+                .is_some_and(|foreground| foreground.eq_ignore_ascii_case(app)),
+        }
+    }
+}
+
+pub struct MacroRun {
+    name: String,
+    steps: Vec<MacroStep>,
+    next: usize,  // 下一个要执行的步骤
+    waited: bool, // 下一个步骤的等待时间已经过去
+}
+
+impl MacroRun {
+    pub fn new(name: String, steps: Vec<MacroStep>) -> Self {
+        Self {
+            name,
+            steps,
+            next: 0,
+            waited: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // 执行到下一个需要等待的步骤为止，返回需等待的毫秒数；全部执行完毕时返回 None
+    pub fn advance(&mut self, backend: &mut impl MacroBackend) -> Option<u32> {
+        while let Some(step) = self.steps.get(self.next) {
+            if step.delay_ms > 0 && !self.waited {
+                self.waited = true;
+                return Some(step.delay_ms);
+            }
+            self.next += 1;
+            self.waited = false;
+
+            if let Action::Macro(inner) = &step.action {
+                warn!("宏 \"{}\" 中不能调用宏 \"{}\"，已跳过", self.name, inner);
+                continue;
+            }
+            if let Some(condition) = &step.when {
+                if !condition.holds(backend) {
+                    info!(
+                        "宏 \"{}\" 第 {} 步条件不满足，跳过: {}",
+                        self.name,
+                        self.next,
+                        step.action.description()
+                    );
+                    continue;
+                }
+            }
+            if let Err(e) = backend.execute(&step.action) {
+                warn!(
+                    "宏 \"{}\" 第 {} 步执行失败，中止执行: {}",
+                    self.name, self.next, e
+                );
+                self.next = self.steps.len();
+                return None;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 记录执行过的动作，并模拟 SetMode / SetMasterSwitch 对状态的影响
+    struct RecordingBackend {
+        mode: ImeMode,
+        master_switch: bool,
+        app: Option<String>,
+        fail_on: Option<Action>,
+        executed: Vec<Action>,
+    }
+
+    impl RecordingBackend {
+        fn new() -> Self {
+            Self {
+                mode: ImeMode::ChineseOnly,
+                master_switch: true,
+                app: Some("Code.exe".to_string()),
+                fail_on: None,
+                executed: Vec::new(),
+            }
+        }
+    }
+
+    impl MacroBackend for RecordingBackend {
+        fn ime_mode(&self) -> ImeMode {
+            self.mode.clone()
+        }
+
+        fn master_switch(&self) -> bool {
+            self.master_switch
+        }
+
+        fn foreground_app(&self) -> Option<String> {
+            self.app.clone()
+        }
+
+        fn execute(&mut self, action: &Action) -> Result<(), String> {
+            self.executed.push(action.clone());
+            if self.fail_on.as_ref() == Some(action) {
+                return Err("模拟失败".to_string());
+            }
+            match action {
+                Action::SetMode(mode) => self.mode = mode.clone(),
+                Action::SetMasterSwitch(on) => self.master_switch = *on,
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    fn step(action: Action, delay_ms: u32, when: Option<MacroCondition>) -> MacroStep {
+        MacroStep {
+            action,
+            delay_ms,
+            when,
+        }
+    }
+
+    fn notify(text: &str) -> Action {
+        Action::Notify(text.to_string())
+    }
+
+    #[test]
+    fn steps_run_in_order() {
+        let mut backend = RecordingBackend::new();
+        let mut run = MacroRun::new(
+            "m".to_string(),
+            vec![
+                step(Action::SetMode(ImeMode::EnglishOnly), 0, None),
+                step(notify("a"), 0, None),
+                step(Action::SetMasterSwitch(false), 0, None),
+            ],
+        );
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(
+            backend.executed,
+            vec![
+                Action::SetMode(ImeMode::EnglishOnly),
+                notify("a"),
+                Action::SetMasterSwitch(false)
+            ]
+        );
+    }
+
+    #[test]
+    fn delays_yield_control() {
+        let mut backend = RecordingBackend::new();
+        let mut run = MacroRun::new(
+            "m".to_string(),
+            vec![
+                step(notify("a"), 0, None),
+                step(notify("b"), 100, None),
+                step(notify("c"), 0, None),
+                step(notify("d"), 50, None),
+            ],
+        );
+        assert_eq!(run.advance(&mut backend), Some(100));
+        assert_eq!(backend.executed, vec![notify("a")]);
+        assert_eq!(run.advance(&mut backend), Some(50));
+        assert_eq!(
+            backend.executed,
+            vec![notify("a"), notify("b"), notify("c")]
+        );
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(backend.executed.len(), 4);
+        // 执行完毕后不再执行任何步骤
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(backend.executed.len(), 4);
+    }
+
+    #[test]
+    fn conditions_see_earlier_steps() {
+        let mut backend = RecordingBackend::new();
+        let mut run = MacroRun::new(
+            "m".to_string(),
+            vec![
+                step(Action::SetMode(ImeMode::EnglishOnly), 0, None),
+                step(
+                    notify("english"),
+                    100,
+                    Some(MacroCondition::Mode(ImeMode::EnglishOnly)),
+                ),
+                step(
+                    notify("chinese"),
+                    0,
+                    Some(MacroCondition::Mode(ImeMode::ChineseOnly)),
+                ),
+            ],
+        );
+        assert_eq!(run.advance(&mut backend), Some(100));
+        // 条件在等待结束后判断
+        backend.mode = ImeMode::ChineseOnly;
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(
+            backend.executed,
+            vec![Action::SetMode(ImeMode::EnglishOnly), notify("chinese")]
+        );
+    }
+
+    #[test]
+    fn app_and_switch_conditions() {
+        let mut backend = RecordingBackend::new();
+        let mut run = MacroRun::new(
+            "m".to_string(),
+            vec![
+                step(
+                    notify("code"),
+                    0,
+                    Some(MacroCondition::App("code.EXE".to_string())),
+                ),
+                step(
+                    notify("wechat"),
+                    0,
+                    Some(MacroCondition::App("WeChat.exe".to_string())),
+                ),
+                step(notify("on"), 0, Some(MacroCondition::MasterSwitch(true))),
+                step(notify("off"), 0, Some(MacroCondition::MasterSwitch(false))),
+            ],
+        );
+        run.advance(&mut backend);
+        assert_eq!(backend.executed, vec![notify("code"), notify("on")]);
+    }
+
+    #[test]
+    fn nested_macros_are_skipped() {
+        let mut backend = RecordingBackend::new();
+        let mut run = MacroRun::new(
+            "m".to_string(),
+            vec![
+                step(Action::Macro("other".to_string()), 0, None),
+                step(notify("a"), 0, None),
+            ],
+        );
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(backend.executed, vec![notify("a")]);
+    }
+
+    #[test]
+    fn failed_step_aborts_run() {
+        let mut backend = RecordingBackend::new();
+        backend.fail_on = Some(Action::SwitchProfile("不存在".to_string()));
+        let mut run = MacroRun::new(
+            "m".to_string(),
+            vec![
+                step(notify("a"), 0, None),
+                step(Action::SwitchProfile("不存在".to_string()), 0, None),
+                step(notify("b"), 100, None),
+                step(notify("c"), 0, None),
+            ],
+        );
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(
+            backend.executed,
+            vec![notify("a"), Action::SwitchProfile("不存在".to_string())]
+        );
+        assert_eq!(run.advance(&mut backend), None);
+        assert_eq!(backend.executed.len(), 2);
+    }
+}
@@ -3,7 +3,12 @@
 mod config;
 mod constants;
 mod hooks;
+mod ime;
+mod keyboard;
 mod logger;
+mod macros;
+mod mouse;
+mod state;
 mod tray;
 mod utils;
 
@@ -17,11 +22,16 @@ use windows::Win32::{
 
 use crate::create_window::create_window;
 use crate::hooks::event_loop_hook::event_hook_callback;
+use crate::hooks::keyboard_hook::remove_keyboard_hook;
+use crate::hooks::mouse_hook::remove_mouse_hook;
 use crate::icon::add_tray_icon;
+use crate::ime::pinyin_settings::{apply_pinyin_settings, revert_pinyin_settings};
 use crate::tray::window_proc;
 use crate::tray::*;
 use crate::utils::check_autostart_status::is_likely_autostart;
+use crate::utils::check_autostart_status::remove_autostart_entry;
 use crate::utils::check_autostart_status::sync_autostart_config;
+use crate::utils::config_watcher::start_config_watcher;
 use crate::utils::hot_key::register_all_hotkeys;
 
 // 设置Windows事件钩子
@@ -63,6 +73,23 @@ fn main() -> windows::core::Result<()> {
         eprintln!("初始化日志系统失败: {}", e);
     }
 
+    // 卸载：还原对系统所做的修改后退出
+    if std::env::args().any(|arg| arg == "--uninstall") {
+        info!("执行卸载清理...");
+        revert_pinyin_settings();
+        remove_autostart_entry();
+        return Ok(());
+    }
+
+    // 用最近的备份替换配置文件后退出
+    if std::env::args().any(|arg| arg == "--restore-config") {
+        match config::restore_previous_config() {
+            Ok(_) => info!("配置恢复完成"),
+            Err(e) => error!("恢复配置失败: {}", e),
+        }
+        return Ok(());
+    }
+
     info!("系统启动中...");
 
     // 检查是否是开机自启动（通过检查启动时间来判断）
@@ -75,15 +102,22 @@ fn main() -> windows::core::Result<()> {
     // 同步开机自启动配置
     sync_autostart_config();
 
+    // 应用配置中的微软拼音设置
+    let pinyin_settings = config::CONFIG.read().unwrap().pinyin_settings.clone();
+    apply_pinyin_settings(&pinyin_settings);
+
     info!("创建主窗口...");
     // 创建主窗口
     let hwnd = create_window(window_proc)?;
     info!("主窗口创建成功，HWND={:?}", hwnd);
+    set_main_window(hwnd);
 
     info!("添加托盘图标...");
     // 添加托盘图标
     add_tray_icon(hwnd)?;
     info!("托盘图标添加成功");
+    config::notify_config_error(hwnd);
+    start_config_watcher();
 
     // 注册热键（开机自启动时延迟注册）
     if is_autostart {
@@ -107,6 +141,8 @@ fn main() -> windows::core::Result<()> {
     unsafe {
         UnhookWinEvent(hook);
     }
+    remove_keyboard_hook();
+    remove_mouse_hook();
 
     info!("应用退出中...");
     Ok(())
@@ -0,0 +1,232 @@
+use crate::mouse::MouseButton;
+use crate::utils::parse_hotkey::modifiers_match;
+
+// 鼠标触发方式的匹配，与平台无关，由鼠标钩子逐个输入鼠标事件。
+// - 按下的按键及修饰键与某个绑定匹配时触发动作，并拦截该次按下及对应的松开
+// - 滚轮倾斜按住时会连续产生事件，间隔小于 WHEEL_REPEAT_MS 的视为同一次倾斜，只触发一次
+// - 未绑定的事件一律放行
+
+const WHEEL_REPEAT_MS: u32 = 400;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MouseInput {
+    pub button: MouseButton,
+    pub down: bool, // 滚轮倾斜始终为 true
+    pub time_ms: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MouseBinding {
+    pub modifiers: u32,
+    pub button: MouseButton,
+    pub id: usize, // 触发时返回的动作编号
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MouseDecision {
+    pub swallow: bool,
+    pub fire: Option<usize>,
+}
+
+pub struct MouseMatcher {
+    bindings: Vec<MouseBinding>,
+    swallowed: Vec<MouseButton>, // 已拦截按下事件、需要同时拦截松开事件的按键
+    last_wheel: Option<(MouseButton, u32)>,
+}
+
+impl MouseMatcher {
+    pub fn new(bindings: Vec<MouseBinding>) -> Self {
+        Self {
+            bindings,
+            swallowed: Vec::new(),
+            last_wheel: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    // pressed 为当前按下的修饰键，只含 MOD_L*/MOD_R* 位
+    pub fn handle(&mut self, input: MouseInput, pressed: u32) -> MouseDecision {
+        if !input.down {
+            let before = self.swallowed.len();
+            self.swallowed.retain(|&button| button != input.button);
+            return MouseDecision {
+                swallow: self.swallowed.len() != before,
+                fire: None,
+            };
+        }
+
+        if input.button.is_wheel() {
+            if let Some((button, time_ms)) = self.last_wheel {
+                if button == input.button && input.time_ms.wrapping_sub(time_ms) < WHEEL_REPEAT_MS {
+                    self.last_wheel = Some((button, input.time_ms));
+                    return MouseDecision {
+                        swallow: true,
+                        fire: None,
+                    };
+                }
+            }
+        }
+
+        let Some(binding) = self
+            .bindings
+            .iter()
+            .find(|b| b.button == input.button && modifiers_match(b.modifiers, pressed))
+        else {
+            return MouseDecision::default();
+        };
+
+        if input.button.is_wheel() {
+            self.last_wheel = Some((input.button, input.time_ms));
+        } else if !self.swallowed.contains(&input.button) {
+            self.swallowed.push(input.button);
+        }
+        MouseDecision {
+            swallow: true,
+            fire: Some(binding.id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mouse::{mouse_button_name, parse_mouse_button, x_button};
+    use crate::utils::parse_hotkey::{MOD_CONTROL, MOD_LCONTROL, MOD_LSHIFT, MOD_RCONTROL};
+
+    fn matcher() -> MouseMatcher {
+        MouseMatcher::new(vec![
+            MouseBinding {
+                modifiers: MOD_CONTROL,
+                button: MouseButton::Middle,
+                id: 0,
+            },
+            MouseBinding {
+                modifiers: 0,
+                button: MouseButton::X1,
+                id: 1,
+            },
+            MouseBinding {
+                modifiers: 0,
+                button: MouseButton::WheelLeft,
+                id: 2,
+            },
+        ])
+    }
+
+    fn input(button: MouseButton, down: bool, time_ms: u32) -> MouseInput {
+        MouseInput {
+            button,
+            down,
+            time_ms,
+        }
+    }
+
+    fn fired(id: usize) -> MouseDecision {
+        MouseDecision {
+            swallow: true,
+            fire: Some(id),
+        }
+    }
+
+    const SWALLOWED: MouseDecision = MouseDecision {
+        swallow: true,
+        fire: None,
+    };
+
+    #[test]
+    fn unbound_buttons_pass_through() {
+        let mut matcher = matcher();
+        for button in [MouseButton::X2, MouseButton::WheelRight] {
+            assert_eq!(
+                matcher.handle(input(button, true, 0), 0),
+                MouseDecision::default()
+            );
+            assert_eq!(
+                matcher.handle(input(button, false, 10), 0),
+                MouseDecision::default()
+            );
+        }
+        // 修饰键不匹配
+        assert_eq!(
+            matcher.handle(input(MouseButton::Middle, true, 0), 0),
+            MouseDecision::default()
+        );
+        assert_eq!(
+            matcher.handle(input(MouseButton::Middle, false, 10), 0),
+            MouseDecision::default()
+        );
+        assert_eq!(
+            matcher.handle(input(MouseButton::X1, true, 20), MOD_LSHIFT),
+            MouseDecision::default()
+        );
+    }
+
+    #[test]
+    fn modifier_button_swallows_down_and_up() {
+        let mut matcher = matcher();
+        for pressed in [MOD_LCONTROL, MOD_RCONTROL] {
+            assert_eq!(
+                matcher.handle(input(MouseButton::Middle, true, 0), pressed),
+                fired(0)
+            );
+            // 松开前先松开了 Ctrl，松开事件仍然拦截
+            assert_eq!(
+                matcher.handle(input(MouseButton::Middle, false, 50), 0),
+                SWALLOWED
+            );
+            // 之后的松开事件不再拦截
+            assert_eq!(
+                matcher.handle(input(MouseButton::Middle, false, 60), 0),
+                MouseDecision::default()
+            );
+        }
+    }
+
+    #[test]
+    fn wheel_tilt_repeats_fire_once() {
+        let mut matcher = matcher();
+        assert_eq!(
+            matcher.handle(input(MouseButton::WheelLeft, true, 0), 0),
+            fired(2)
+        );
+        assert_eq!(
+            matcher.handle(input(MouseButton::WheelLeft, true, 100), 0),
+            SWALLOWED
+        );
+        assert_eq!(
+            matcher.handle(input(MouseButton::WheelLeft, true, 450), 0),
+            SWALLOWED
+        );
+        assert_eq!(
+            matcher.handle(input(MouseButton::WheelLeft, true, 900), 0),
+            fired(2)
+        );
+    }
+
+    #[test]
+    fn x_buttons_map_to_names() {
+        assert_eq!(x_button(1), Some(MouseButton::X1));
+        assert_eq!(x_button(2), Some(MouseButton::X2));
+        assert_eq!(x_button(0), None);
+        assert_eq!(x_button(3), None);
+
+        assert_eq!(parse_mouse_button("XButton1"), Some(MouseButton::X1));
+        assert_eq!(parse_mouse_button("mouse4"), Some(MouseButton::X1));
+        assert_eq!(parse_mouse_button("XButton2"), Some(MouseButton::X2));
+        assert_eq!(parse_mouse_button("Mouse5"), Some(MouseButton::X2));
+        assert_eq!(mouse_button_name(MouseButton::X1), "XButton1");
+        assert_eq!(mouse_button_name(MouseButton::X2), "XButton2");
+
+        let mut matcher = matcher();
+        let x2 = x_button(2).unwrap();
+        assert_eq!(
+            matcher.handle(input(x2, true, 0), 0),
+            MouseDecision::default()
+        );
+        let x1 = x_button(1).unwrap();
+        assert_eq!(matcher.handle(input(x1, true, 0), 0), fired(1));
+    }
+}
@@ -0,0 +1,54 @@
+pub mod matcher;
+
+// 可绑定的鼠标按键。左右键过于常用，不支持绑定
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum MouseButton {
+    Middle,
+    X1,
+    X2,
+    WheelLeft,  // 滚轮左倾
+    WheelRight, // 滚轮右倾
+}
+
+impl MouseButton {
+    // 滚轮倾斜没有松开事件
+    pub fn is_wheel(&self) -> bool {
+        matches!(self, MouseButton::WheelLeft | MouseButton::WheelRight)
+    }
+}
+
+// WM_XBUTTONDOWN/WM_XBUTTONUP 的 mouseData 高位字对应的按键：1 为 XBUTTON1（后退），2 为 XBUTTON2（前进）
+pub fn x_button(high_word: u16) -> Option<MouseButton> {
+    match high_word {
+        1 => Some(MouseButton::X1),
+        2 => Some(MouseButton::X2),
+        _ => None,
+    }
+}
+
+// 鼠标按键名称。同一按键的第一个名称为规范名称，之后的为别名；匹配时不区分大小写
+pub const MOUSE_BUTTON_TABLE: &[(&str, MouseButton)] = &[
+    ("MButton", MouseButton::Middle),
+    ("MiddleButton", MouseButton::Middle),
+    ("XButton1", MouseButton::X1),
+    ("Mouse4", MouseButton::X1),
+    ("XButton2", MouseButton::X2),
+    ("Mouse5", MouseButton::X2),
+    ("WheelLeft", MouseButton::WheelLeft),
+    ("WheelRight", MouseButton::WheelRight),
+];
+
+pub fn parse_mouse_button(name: &str) -> Option<MouseButton> {
+    MOUSE_BUTTON_TABLE
+        .iter()
+        .find(|(button_name, _)| button_name.eq_ignore_ascii_case(name.trim()))
+        .map(|&(_, button)| button)
+}
+
+pub fn mouse_button_name(button: MouseButton) -> &'static str {
+    MOUSE_BUTTON_TABLE
+        .iter()
+        .find(|&&(_, b)| b == button)
+        .map(|&(name, _)| name)
+        .unwrap_or_default()
+}
@@ -0,0 +1,154 @@
+use crate::config::ImeMode;
+use crate::utils::config_file::write_atomic;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+// 程序的运行状态，由程序自己维护，保存在配置文件所在目录的 state.json 中。
+// 与用户编辑的配置文件分开：切换总开关、输入法模式或配置方案不会改写配置文件，也不会触发配置的自动重新加载。
+// 按程序设置的模式（app_modes）由用户编写，属于配置；程序不记录各程序最近使用的模式，因此没有按程序的运行状态
+
+pub const STATE_FILE_NAME: &str = "state.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct State {
+    pub master_switch: bool, // 总开关
+    pub ime_mode: ImeMode,   // 当前强制的输入法模式
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>, // 当前的配置方案，None 表示使用顶层的设置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_backup: Option<PathBuf>, // 最近一次恢复配置所用的备份，连续恢复时从它之前的备份继续
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            master_switch: true, // 默认总开关为开启
+            ime_mode: ImeMode::default(),
+            active_profile: None,
+            restored_backup: None,
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref STATE: RwLock<State> = RwLock::new(State::load());
+}
+
+impl State {
+    fn path() -> PathBuf {
+        crate::config::Config::config_dir().join(STATE_FILE_NAME)
+    }
+
+    fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    // 读取状态文件，文件不存在或有误时使用初始状态
+    fn load_from(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(state) => return state,
+                Err(e) => warn!("状态文件 {:?} 有误，使用初始状态: {}", path, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("读取状态文件 {:?} 失败，使用初始状态: {}", path, e),
+        }
+        State::default()
+    }
+
+    fn save(&self) {
+        self.save_to(&Self::path());
+    }
+
+    fn save_to(&self, path: &Path) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(std::io::Error::other)
+            .and_then(|content| write_atomic(path, &content));
+        if let Err(e) = result {
+            error!("保存状态文件 {:?} 失败: {}", path, e);
+        }
+    }
+}
+
+// 旧版本的总开关和输入法模式保存在配置文件中，状态文件不存在时（从旧版本升级后首次运行）以其作为初始状态。
+// 在加载配置时调用，早于首次读取 STATE。返回状态文件是否已存在，存在时配置文件中的旧字段可以删除
+pub fn seed_from_legacy_config(master_switch: Option<bool>, ime_mode: Option<ImeMode>) -> bool {
+    seed_at(&State::path(), master_switch, ime_mode)
+}
+
+fn seed_at(path: &Path, master_switch: Option<bool>, ime_mode: Option<ImeMode>) -> bool {
+    if path.exists() {
+        return true;
+    }
+    let state = State {
+        master_switch: master_switch.unwrap_or(true),
+        ime_mode: ime_mode.unwrap_or_default(),
+        active_profile: None,
+        restored_backup: None,
+    };
+    info!("以旧版配置中的运行状态创建状态文件 {:?}: {:?}", path, state);
+    state.save_to(path);
+    path.exists()
+}
+
+// 修改并保存运行状态，返回修改后的状态
+pub fn update_state(update: impl FnOnce(&mut State)) -> State {
+    let mut state = STATE.write().unwrap();
+    update(&mut state);
+    state.save();
+    state.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::TempDir;
+
+    #[test]
+    fn seeds_state_from_legacy_config() {
+        let dir = TempDir::new("state-seed");
+        let path = dir.join(STATE_FILE_NAME);
+        assert!(seed_at(&path, Some(false), Some(ImeMode::EnglishOnly)));
+
+        let state = State::load_from(&path);
+        assert!(!state.master_switch);
+        assert_eq!(state.ime_mode, ImeMode::EnglishOnly);
+        assert_eq!(state.active_profile, None);
+    }
+
+    #[test]
+    fn seeding_keeps_existing_state() {
+        let dir = TempDir::new("state-keep");
+        let path = dir.join(STATE_FILE_NAME);
+        let content = "{\"master_switch\": true, \"active_profile\": \"工作\"}";
+        fs::write(&path, content).unwrap();
+
+        assert!(seed_at(&path, Some(false), Some(ImeMode::EnglishOnly)));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        let state = State::load_from(&path);
+        assert!(state.master_switch);
+        assert_eq!(state.ime_mode, ImeMode::ChineseOnly);
+        assert_eq!(state.active_profile.as_deref(), Some("工作"));
+    }
+
+    #[test]
+    fn missing_or_corrupt_file_uses_defaults() {
+        let dir = TempDir::new("state-corrupt");
+        let path = dir.join(STATE_FILE_NAME);
+        let state = State::load_from(&path);
+        assert!(state.master_switch);
+        assert_eq!(state.ime_mode, ImeMode::ChineseOnly);
+
+        fs::write(&path, "{\"master_switch\": fal").unwrap();
+        let state = State::load_from(&path);
+        assert!(state.master_switch);
+        assert_eq!(state.ime_mode, ImeMode::ChineseOnly);
+        assert_eq!(state.active_profile, None);
+        assert_eq!(state.restored_backup, None);
+    }
+}
@@ -1,44 +1,180 @@
-use crate::config::{apply_ime_setting_to_current_window, ImeMode, CONFIG};
-use crate::constants;
+use crate::config::{apply_ime_setting_to_current_window, Action, ImeMode, CONFIG};
+use crate::hooks::event_loop_hook::{override_window, toggle_pause};
+use crate::hooks::keyboard_hook::hook_action;
+use crate::hooks::mouse_hook::mouse_action;
+use crate::macros::{MacroBackend, MacroRun};
+use crate::state::{update_state, STATE};
 use crate::tray::icon;
 use crate::tray::notifications::show_balloon_tip;
-use log::info;
-use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use crate::utils::hot_key::action_for_hotkey_id;
+use crate::utils::window_process::process_name_of_window;
+use lazy_static::lazy_static;
+use log::{error, info};
+use std::sync::Mutex;
+use windows::Win32::Foundation::{HWND, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, KillTimer, SetTimer};
 
 pub fn handle_hotkey(hwnd: HWND, wparam: WPARAM) {
-    match wparam.0 as i32 {
-        constants::HOT_KEY_TOGGLE_ID => {
-            info!("热键触发: 切换总开关");
-            let mut config = CONFIG.write().unwrap();
-            config.master_switch = !config.master_switch;
-            config.save().ok();
+    if let Some(action) = action_for_hotkey_id(wparam.0 as i32) {
+        info!("热键触发: {}", action.description());
+        execute_action(hwnd, &action);
+    }
+}
 
-            drop(config);
+// 键盘钩子触发的动作
+pub fn handle_hook_action(hwnd: HWND, wparam: WPARAM) {
+    if let Some(action) = hook_action(wparam.0) {
+        info!("键盘钩子触发: {}", action.description());
+        execute_action(hwnd, &action);
+    }
+}
 
-            let _ = icon::update_tray_icon(hwnd, CONFIG.read().unwrap().master_switch);
+// 鼠标钩子触发的动作
+pub fn handle_mouse_action(hwnd: HWND, wparam: WPARAM) {
+    if let Some(action) = mouse_action(wparam.0) {
+        info!("鼠标钩子触发: {}", action.description());
+        execute_action(hwnd, &action);
+    }
+}
 
-            let msg = if CONFIG.read().unwrap().master_switch {
-                "已启用强制输入法模式"
-            } else {
-                "已恢复输入法自动切换"
-            };
+// 执行绑定的动作，失败时已在执行过程中提示
+pub fn execute_action(hwnd: HWND, action: &Action) {
+    let _ = try_execute_action(hwnd, action);
+}
 
-            if CONFIG.read().unwrap().show_notifications {
-                show_balloon_tip(hwnd, "快捷键触发", msg);
-            }
+// 执行动作，返回失败的原因（供宏中止执行）
+fn try_execute_action(hwnd: HWND, action: &Action) -> Result<(), String> {
+    match action {
+        Action::ToggleMasterSwitch => {
+            let on = !STATE.read().unwrap().master_switch;
+            set_master_switch(hwnd, on);
         }
-        constants::HOT_KEY_SWITCH_MODE_ID => {
-            info!("热键触发: 切换输入法模式");
-            let mut config = CONFIG.write().unwrap();
-            let new_mode = match config.ime_mode {
+        Action::SetMasterSwitch(on) => set_master_switch(hwnd, *on),
+        Action::SetMode(mode) => set_ime_mode(hwnd, mode.clone()),
+        Action::CycleMode => {
+            let new_mode = match STATE.read().unwrap().ime_mode {
                 ImeMode::ChineseOnly => ImeMode::EnglishOnly,
                 ImeMode::EnglishOnly => ImeMode::ChineseOnly,
             };
-            config.ime_mode = new_mode.clone();
-            config.save().ok();
-            drop(config);
-            apply_ime_setting_to_current_window(hwnd, new_mode);
+            set_ime_mode(hwnd, new_mode);
+        }
+        Action::TogglePause => {
+            let paused = toggle_pause();
+            if CONFIG.read().unwrap().show_notifications() {
+                show_balloon_tip(
+                    hwnd,
+                    "快捷键触发",
+                    if paused {
+                        "已暂停强制输入法模式"
+                    } else {
+                        "已恢复强制输入法模式"
+                    },
+                );
+            }
+        }
+        Action::OverrideCurrentWindow => {
+            override_window(unsafe { GetForegroundWindow() });
+            if CONFIG.read().unwrap().show_notifications() {
+                show_balloon_tip(hwnd, "快捷键触发", "当前窗口临时不强制输入法模式");
+            }
         }
-        _ => {}
+        Action::ReloadConfig => crate::config::reload_config_and_hotkeys(hwnd),
+        Action::OpenConfigDir => crate::config::open_config_directory(),
+        Action::ExcludeCurrentApp => crate::config::exclude_current_app(hwnd),
+        Action::ToggleNotifications => crate::config::toggle_notifications(hwnd),
+        Action::Notify(text) => show_balloon_tip(hwnd, "IME Controller", text),
+        Action::Macro(name) => start_macro(hwnd, name),
+        Action::SwitchProfile(name) => return crate::config::switch_profile(hwnd, Some(name)),
+        Action::ClearProfile => return crate::config::switch_profile(hwnd, None),
+    }
+    Ok(())
+}
+
+fn set_master_switch(hwnd: HWND, on: bool) {
+    update_state(|state| state.master_switch = on);
+
+    let _ = icon::update_tray_icon(hwnd, on);
+
+    let msg = if on {
+        "已启用强制输入法模式"
+    } else {
+        "已恢复输入法自动切换"
+    };
+
+    if CONFIG.read().unwrap().show_notifications() {
+        show_balloon_tip(hwnd, "快捷键触发", msg);
     }
 }
+
+lazy_static! {
+    // 正在等待延时的宏，同一时间只运行一个
+    static ref RUNNING_MACRO: Mutex<Option<MacroRun>> = Mutex::new(None);
+}
+
+// 宏通过此后端读取程序状态并执行动作
+struct AppMacroBackend {
+    hwnd: HWND,
+}
+
+impl MacroBackend for AppMacroBackend {
+    fn ime_mode(&self) -> ImeMode {
+        STATE.read().unwrap().ime_mode.clone()
+    }
+
+    fn master_switch(&self) -> bool {
+        STATE.read().unwrap().master_switch
+    }
+
+    fn foreground_app(&self) -> Option<String> {
+        process_name_of_window(unsafe { GetForegroundWindow() })
+    }
+
+    fn execute(&mut self, action: &Action) -> Result<(), String> {
+        info!("宏执行: {}", action.description());
+        try_execute_action(self.hwnd, action)
+    }
+}
+
+// 开始执行宏，会取消正在等待的宏
+fn start_macro(hwnd: HWND, name: &str) {
+    let Some(steps) = CONFIG.read().unwrap().macros.get(name).cloned() else {
+        error!("宏 \"{}\" 不存在", name);
+        return;
+    };
+    unsafe {
+        let _ = KillTimer(Some(hwnd), crate::constants::TIMER_ID_MACRO);
+    }
+    let previous = RUNNING_MACRO.lock().unwrap().take();
+    if let Some(previous) = previous {
+        info!("取消未执行完的宏: {}", previous.name());
+    }
+    run_macro(hwnd, MacroRun::new(name.to_string(), steps));
+}
+
+// 宏的延时结束，继续执行
+pub fn continue_macro(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(Some(hwnd), crate::constants::TIMER_ID_MACRO);
+    }
+    let run = RUNNING_MACRO.lock().unwrap().take();
+    if let Some(run) = run {
+        run_macro(hwnd, run);
+    }
+}
+
+fn run_macro(hwnd: HWND, mut run: MacroRun) {
+    match run.advance(&mut AppMacroBackend { hwnd }) {
+        Some(delay_ms) => {
+            *RUNNING_MACRO.lock().unwrap() = Some(run);
+            unsafe {
+                SetTimer(Some(hwnd), crate::constants::TIMER_ID_MACRO, delay_ms, None);
+            }
+        }
+        None => info!("宏执行完成: {}", run.name()),
+    }
+}
+
+fn set_ime_mode(hwnd: HWND, mode: ImeMode) {
+    update_state(|state| state.ime_mode = mode.clone());
+    apply_ime_setting_to_current_window(hwnd, mode);
+}
@@ -1,5 +1,6 @@
-use crate::config::CONFIG;
+use crate::hooks::suspend::suspended_by;
 use crate::constants;
+use crate::state::STATE;
 use crate::tray::HINSTANCE;
 use log::info;
 use windows::{
@@ -20,11 +21,7 @@ pub fn update_tray_icon(hwnd: HWND, enabled: bool) -> windows::core::Result<()>
     } else {
         constants::IDI_ICON1
     };
-    let tooltip = if enabled {
-        "输入法控制 - 已启用"
-    } else {
-        "输入法控制 - 已禁用"
-    };
+    let tooltip = tooltip_text(enabled);
 
     info!("更新托盘图标 - enabled: {}, icon_id: {}", enabled, icon_id);
 
@@ -51,23 +48,28 @@ pub fn update_tray_icon(hwnd: HWND, enabled: bool) -> windows::core::Result<()>
     Ok(())
 }
 
+// 托盘提示文字，游戏模式下注明暂停原因
+fn tooltip_text(enabled: bool) -> String {
+    let state = if enabled { "已启用" } else { "已禁用" };
+    match suspended_by() {
+        Some(app) => format!("输入法控制 - {} (游戏模式已暂停: {})", state, app),
+        None => format!("输入法控制 - {}", state),
+    }
+}
+
 // 添加托盘图标
 pub fn add_tray_icon(hwnd: HWND) -> windows::core::Result<()> {
     let h_instance = unsafe { GetModuleHandleW(None) }?;
-    let config = CONFIG.read().unwrap();
+    let master_switch = STATE.read().unwrap().master_switch;
 
-    let icon_id = if config.master_switch {
+    let icon_id = if master_switch {
         constants::IDI_ICON_ACTIVE
     } else {
         constants::IDI_ICON1
     };
-    let tooltip = if config.master_switch {
-        "输入法控制 - 已启用"
-    } else {
-        "输入法控制 - 已禁用"
-    };
+    let tooltip = tooltip_text(master_switch);
 
-    info!("配置: {}", config.master_switch);
+    info!("总开关: {}", master_switch);
     info!("加载图标 ID: {}", icon_id);
     info!("添加托盘图标提示: {}", tooltip);
 
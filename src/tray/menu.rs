@@ -1,4 +1,9 @@
 use crate::config::{ImeMode, CONFIG};
+use crate::state::STATE;
+use crate::utils::hot_key::{
+    accept_alternative, binding_statuses, hotkey_conflicts, retry_failed_hotkeys, HotkeyState,
+    MAX_ALTERNATIVES,
+};
 use log::info;
 use windows::Win32::UI::WindowsAndMessaging::*;
 use windows::{
@@ -22,6 +27,14 @@ fn append_menu_w(hmenu: HMENU, flags: MENU_ITEM_FLAGS, id: u32, text: &str) {
     }
 }
 
+// 菜单项 ID 不能超出各自的范围：热键冲突为 IDM_HOTKEY_ALTERNATIVE_BASE..IDM_PROFILE_BASE，
+// 配置方案为 IDM_PROFILE_BASE..IDM_PROFILE_END，超出的部分不在菜单中列出
+const MAX_CONFLICTS_LISTED: usize = (crate::constants::IDM_PROFILE_BASE
+    - crate::constants::IDM_HOTKEY_ALTERNATIVE_BASE) as usize
+    / MAX_ALTERNATIVES;
+const MAX_PROFILES_LISTED: usize =
+    (crate::constants::IDM_PROFILE_END - crate::constants::IDM_PROFILE_BASE) as usize;
+
 pub fn show_tray_menu(hwnd: HWND) {
     unsafe {
         let mut point = POINT::default();
@@ -29,11 +42,12 @@ pub fn show_tray_menu(hwnd: HWND) {
 
         let hmenu = CreatePopupMenu().unwrap();
         let config = CONFIG.read().unwrap();
+        let state = STATE.read().unwrap().clone();
 
         // 添加菜单项
         append_menu_w(
             hmenu,
-            if config.master_switch {
+            if state.master_switch {
                 MF_CHECKED
             } else {
                 MF_UNCHECKED
@@ -57,7 +71,7 @@ pub fn show_tray_menu(hwnd: HWND) {
 
         append_menu_w(
             hmenu,
-            if config.show_notifications {
+            if config.show_notifications() {
                 MF_CHECKED
             } else {
                 MF_UNCHECKED
@@ -70,7 +84,7 @@ pub fn show_tray_menu(hwnd: HWND) {
         let mode_menu = CreatePopupMenu().unwrap();
         append_menu_w(
             mode_menu,
-            if config.ime_mode == ImeMode::ChineseOnly {
+            if state.ime_mode == ImeMode::ChineseOnly {
                 MF_CHECKED
             } else {
                 MF_UNCHECKED
@@ -80,7 +94,7 @@ pub fn show_tray_menu(hwnd: HWND) {
         );
         append_menu_w(
             mode_menu,
-            if config.ime_mode == ImeMode::EnglishOnly {
+            if state.ime_mode == ImeMode::EnglishOnly {
                 MF_CHECKED
             } else {
                 MF_UNCHECKED
@@ -90,6 +104,106 @@ pub fn show_tray_menu(hwnd: HWND) {
         );
         append_menu_w(hmenu, MF_POPUP, mode_menu.0 as u32, "输入法模式");
 
+        // 配置方案子菜单
+        if !config.profiles.is_empty() {
+            let profile_menu = CreatePopupMenu().unwrap();
+            append_menu_w(
+                profile_menu,
+                if config.active_profile.is_none() {
+                    MF_CHECKED
+                } else {
+                    MF_UNCHECKED
+                },
+                crate::constants::IDM_PROFILE_NONE,
+                "默认设置",
+            );
+            let _ = AppendMenuW(profile_menu, MF_SEPARATOR, 0, PCWSTR::null());
+            for (i, name) in config.profiles.keys().enumerate().take(MAX_PROFILES_LISTED) {
+                append_menu_w(
+                    profile_menu,
+                    if config.active_profile.as_ref() == Some(name) {
+                        MF_CHECKED
+                    } else {
+                        MF_UNCHECKED
+                    },
+                    crate::constants::IDM_PROFILE_BASE + i as u32,
+                    name,
+                );
+            }
+            append_menu_w(hmenu, MF_POPUP, profile_menu.0 as u32, "配置方案");
+        }
+
+        // 热键状态子菜单：列出每个绑定的注册状态，有失败的热键时可立即重试
+        let statuses = binding_statuses();
+        if !statuses.is_empty() {
+            let status_menu = CreatePopupMenu().unwrap();
+            for status in &statuses {
+                append_menu_w(
+                    status_menu,
+                    if status.state == HotkeyState::Registered {
+                        MF_CHECKED | MF_DISABLED
+                    } else {
+                        MF_DISABLED
+                    },
+                    0,
+                    &format!(
+                        "{} -> {}: {}",
+                        status.trigger,
+                        status.action.description(),
+                        status.state
+                    ),
+                );
+            }
+            if statuses
+                .iter()
+                .any(|s| matches!(s.state, HotkeyState::Failed { .. }))
+            {
+                let _ = AppendMenuW(status_menu, MF_SEPARATOR, 0, PCWSTR::null());
+                append_menu_w(
+                    status_menu,
+                    MF_ENABLED,
+                    crate::constants::IDM_RETRY_HOTKEYS,
+                    "重试注册失败的热键",
+                );
+            }
+            append_menu_w(hmenu, MF_POPUP, status_menu.0 as u32, "热键状态");
+        }
+
+        // 热键冲突子菜单：为每个被占用的热键列出可用的替代组合
+        let conflicts = hotkey_conflicts();
+        if conflicts.iter().any(|c| !c.alternatives.is_empty()) {
+            let conflict_menu = CreatePopupMenu().unwrap();
+            for (i, conflict) in conflicts.iter().enumerate().take(MAX_CONFLICTS_LISTED) {
+                for (j, alternative) in conflict.alternatives.iter().enumerate() {
+                    append_menu_w(
+                        conflict_menu,
+                        MF_ENABLED,
+                        crate::constants::IDM_HOTKEY_ALTERNATIVE_BASE
+                            + (i * MAX_ALTERNATIVES + j) as u32,
+                        &format!(
+                            "{}: {} 改为 {}",
+                            conflict.action.description(),
+                            conflict.hotkey,
+                            alternative
+                        ),
+                    );
+                }
+            }
+            if conflicts.len() > MAX_CONFLICTS_LISTED {
+                append_menu_w(
+                    conflict_menu,
+                    MF_DISABLED,
+                    0,
+                    &format!(
+                        "另有 {} 个冲突未列出，请查看日志",
+                        conflicts.len() - MAX_CONFLICTS_LISTED
+                    ),
+                );
+            }
+            let _ = AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
+            append_menu_w(hmenu, MF_POPUP, conflict_menu.0 as u32, "热键冲突");
+        }
+
         AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
         append_menu_w(
             hmenu,
@@ -109,6 +223,12 @@ pub fn show_tray_menu(hwnd: HWND) {
             crate::constants::IDM_RELOAD_CONFIG,
             "重新加载配置",
         );
+        append_menu_w(
+            hmenu,
+            MF_ENABLED,
+            crate::constants::IDM_RESTORE_CONFIG,
+            "恢复上一个配置",
+        );
         AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null());
         append_menu_w(hmenu, MF_ENABLED, crate::constants::IDM_EXIT, "退出");
 
@@ -146,6 +266,10 @@ pub fn handle_menu_command(hwnd: HWND, wparam: WPARAM) {
             info!("重新加载配置");
             crate::config::reload_config_and_hotkeys(hwnd);
         }
+        crate::constants::IDM_RESTORE_CONFIG => {
+            info!("恢复上一个配置");
+            crate::config::restore_previous_config_and_reload(hwnd);
+        }
         crate::constants::IDM_IME_CHINESE => {
             info!("切换输入法模式: 中文");
             crate::config::switch_ime_mode(hwnd, crate::config::ImeMode::ChineseOnly);
@@ -158,6 +282,32 @@ pub fn handle_menu_command(hwnd: HWND, wparam: WPARAM) {
             info!("切换主开关");
             crate::config::toggle_master_switch(hwnd);
         }
+        crate::constants::IDM_RETRY_HOTKEYS => {
+            info!("重试注册失败的热键");
+            retry_failed_hotkeys(hwnd);
+        }
+        crate::constants::IDM_PROFILE_NONE => {
+            info!("取消配置方案");
+            let _ = crate::config::switch_profile(hwnd, None);
+        }
+        cmd if (crate::constants::IDM_PROFILE_BASE..crate::constants::IDM_PROFILE_END)
+            .contains(&cmd) =>
+        {
+            let index = (cmd - crate::constants::IDM_PROFILE_BASE) as usize;
+            let name = CONFIG.read().unwrap().profiles.keys().nth(index).cloned();
+            if let Some(name) = name {
+                info!("切换配置方案: {}", name);
+                let _ = crate::config::switch_profile(hwnd, Some(&name));
+            }
+        }
+        cmd if (crate::constants::IDM_HOTKEY_ALTERNATIVE_BASE
+            ..crate::constants::IDM_PROFILE_BASE)
+            .contains(&cmd) =>
+        {
+            let index = (cmd - crate::constants::IDM_HOTKEY_ALTERNATIVE_BASE) as usize;
+            info!("采用替代热键: {}", index);
+            accept_alternative(hwnd, index / MAX_ALTERNATIVES, index % MAX_ALTERNATIVES);
+        }
         _ => {}
     }
 }
@@ -10,9 +10,21 @@ use windows::Win32::UI::WindowsAndMessaging::*;
 use crate::tray::hotkey::*;
 use crate::tray::menu::*;
 use log::info;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub const NOTIFYICON_MESSAGE: u32 = WM_USER + 100;
 
+// 主窗口句柄，供后台线程显示托盘通知
+static MAIN_WINDOW: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_main_window(hwnd: HWND) {
+    MAIN_WINDOW.store(hwnd.0 as usize, Ordering::Relaxed);
+}
+
+pub fn main_window() -> HWND {
+    HWND(MAIN_WINDOW.load(Ordering::Relaxed) as *mut core::ffi::c_void)
+}
+
 pub unsafe extern "system" fn window_proc(
     hwnd: HWND,
     msg: u32,
@@ -24,6 +36,19 @@ pub unsafe extern "system" fn window_proc(
         NOTIFYICON_MESSAGE => handle_tray_message(hwnd, lparam),
         WM_COMMAND => handle_menu_command(hwnd, wparam),
         WM_HOTKEY => handle_hotkey(hwnd, wparam),
+        WM_TIMER if wparam.0 == crate::constants::TIMER_ID_KEYSTROKE_VERIFY => {
+            crate::hooks::event_loop_hook::continue_keystroke_enforcement(hwnd)
+        }
+        crate::constants::WM_HOOK_ACTION => handle_hook_action(hwnd, wparam),
+        crate::constants::WM_MOUSE_ACTION => handle_mouse_action(hwnd, wparam),
+        WM_TIMER if wparam.0 == crate::constants::TIMER_ID_SUSPEND_CHECK => {
+            crate::hooks::suspend::recheck_suspension()
+        }
+        WM_TIMER if wparam.0 == crate::constants::TIMER_ID_HOTKEY_RETRY => {
+            crate::utils::hot_key::retry_hotkey_registration(hwnd)
+        }
+        WM_TIMER if wparam.0 == crate::constants::TIMER_ID_MACRO => continue_macro(hwnd),
+        crate::constants::WM_CONFIG_CHANGED => crate::config::reload_config_if_changed(hwnd),
         WM_DESTROY => {
             info!("窗口销毁，退出消息循环");
             PostQuitMessage(0);
@@ -26,35 +26,32 @@ pub fn show_balloon_tip(hwnd: HWND, title: &str, msg: &str) {
 }
 
 pub fn show_hotkey_config_info(hwnd: HWND) {
-    let config = CONFIG.read().unwrap();        
-
-    let toggle_key = config
-        .hotkey_toggle
-        .as_ref()
-        .unwrap_or(&"未设置".to_string())
-        .clone();
-
-    let switch_mode_key = config
-        .hotkey_switch_mode
-        .as_ref()
-        .unwrap_or(&"未设置".to_string())
-        .clone();
+    let config = CONFIG.read().unwrap();
+
+    let bindings = if config.bindings().is_empty() {
+        "未设置\n".to_string()
+    } else {
+        config
+            .bindings()
+            .iter()
+            .map(|(hotkey, action)| format!("{}: {}\n", action.description(), hotkey))
+            .collect::<String>()
+    };
 
     drop(config);
 
     let message = format!(
         "当前快捷键设置：\n\n\
-        总开关切换: {}\n\
-        中英文模式切换: {}\n\n\
+        {}\n\
         如需修改快捷键：\n\
         1. 右键托盘图标 → 打开配置目录\n\
-        2. 编辑 config.json 文件\n\
+        2. 编辑配置文件中的 bindings\n\
         3. 右键托盘图标 → 重新加载配置\n\n\
         快捷键格式示例：\n\
         Alt+S, Ctrl+F, Shift+F1, Win+Space\n\
         支持的修饰键: Alt, Ctrl, Shift, Win\n\
-        支持的按键: A-Z, F1-F12, Space, Enter, Esc, Tab",
-        toggle_key, switch_mode_key
+        支持的按键: A-Z, 0-9, F1-F24, 方向键, Home/End, 小键盘, 标点, VK_0x..",
+        bindings
     );
 
     show_balloon_tip(hwnd, "快捷键配置帮助", &message);
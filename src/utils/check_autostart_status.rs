@@ -72,3 +72,18 @@ pub fn is_likely_autostart() -> bool {
         Err(_) => false,
     }
 }
+
+// 移除开机自启动项（卸载时使用）
+pub fn remove_autostart_entry() {
+    match RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(
+        "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run",
+        KEY_WRITE,
+    ) {
+        Ok(run_key) => {
+            if run_key.delete_value("IME Controller").is_ok() {
+                info!("已移除开机自启动项");
+            }
+        }
+        Err(e) => error!("无法访问注册表Run键: {:?}", e),
+    }
+}
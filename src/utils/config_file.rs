@@ -0,0 +1,216 @@
+use log::warn;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+// 配置文件的写入：先写临时文件并刷到磁盘，再重命名替换原文件，避免写到一半时崩溃或断电留下残缺的文件。
+// 内容有变化时，原文件先另存为带时间戳的备份（如 config.json.20250101-120000-000.bak），只保留最近 keep 个
+
+// 在文件名后追加后缀，如 config.json -> config.json.tmp
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let temp_path = with_suffix(path, ".tmp");
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+// 写入配置文件并备份原内容，返回是否实际写入（内容未变化时不写入）
+pub fn save_with_backup(path: &Path, content: &str, keep: usize) -> io::Result<bool> {
+    match fs::read_to_string(path) {
+        Ok(old) if old == content => return Ok(false),
+        Ok(old) if keep > 0 => write_atomic(&backup_path(path), &old)?,
+        _ => {}
+    }
+    write_atomic(path, content)?;
+    prune_backups(path, keep);
+    Ok(true)
+}
+
+// 备份文件名使用当前时间，同名的备份已存在时顺延一毫秒，避免覆盖
+fn backup_path(path: &Path) -> PathBuf {
+    let mut now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    loop {
+        let backup = timestamped(path, now);
+        if !backup.exists() {
+            return backup;
+        }
+        now += time::Duration::milliseconds(1);
+    }
+}
+
+fn timestamped(path: &Path, now: OffsetDateTime) -> PathBuf {
+    with_suffix(
+        path,
+        &format!(
+            ".{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.bak",
+            now.year(),
+            now.month() as u8,
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second(),
+            now.millisecond()
+        ),
+    )
+}
+
+// 配置文件的所有时间戳备份，最新的在前
+pub fn list_backups(path: &Path) -> Vec<PathBuf> {
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let prefix = format!(
+        "{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|backup| {
+            let name = backup.file_name().unwrap_or_default().to_string_lossy();
+            name.strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .is_some_and(is_timestamp)
+        })
+        .collect();
+    // 时间戳格式固定，按文件名排序即按时间排序
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+// 形如 20250101-120000-000
+fn is_timestamp(s: &str) -> bool {
+    s.len() == 19
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 15 => c == '-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+fn prune_backups(path: &Path, keep: usize) {
+    for backup in list_backups(path).into_iter().skip(keep) {
+        if let Err(e) = fs::remove_file(&backup) {
+            warn!("删除旧的配置备份 {:?} 失败: {}", backup, e);
+        }
+    }
+}
+
+// 用最近一个与当前内容不同的备份替换配置文件，返回所用的备份。
+// last_restored 为上次恢复所用的备份：恢复后配置文件没有修改过时，从该备份之前的备份继续往前找，
+// 因此连续恢复会逐个回到更早的版本，而不是在最近的两个版本之间来回切换。
+// 当前内容没有相同的备份时先备份，保证恢复后仍能回到当前内容；恢复时不删除旧备份，下次保存时再清理
+pub fn restore_previous(path: &Path, last_restored: Option<&Path>) -> io::Result<PathBuf> {
+    let current = fs::read_to_string(path).ok();
+    let backups = list_backups(path);
+    let contents = backups
+        .iter()
+        .map(fs::read_to_string)
+        .collect::<io::Result<Vec<String>>>()?;
+    let is_current = |i: usize| current.as_deref() == Some(contents[i].as_str());
+
+    let start = last_restored
+        .and_then(|last| backups.iter().position(|backup| backup == last))
+        .filter(|&i| is_current(i))
+        .map_or(0, |i| i + 1);
+    let Some(index) = (start..backups.len()).find(|&i| !is_current(i)) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "没有更早的配置备份可以恢复",
+        ));
+    };
+
+    if let Some(current) = &current {
+        if !contents.contains(current) {
+            write_atomic(&backup_path(path), current)?;
+        }
+    }
+    write_atomic(path, &contents[index])?;
+    Ok(backups[index].clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::TempDir;
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn save_keeps_rotating_backups() {
+        let dir = TempDir::new("rotate");
+        let path = dir.join("config.json");
+        for i in 1..=5 {
+            assert!(save_with_backup(&path, &format!("v{}", i), 3).unwrap());
+        }
+        assert!(!save_with_backup(&path, "v5", 3).unwrap());
+
+        let backups: Vec<String> = list_backups(&path).iter().map(|b| read(b)).collect();
+        assert_eq!(backups, vec!["v4", "v3", "v2"]);
+        assert_eq!(read(&path), "v5");
+        // 不留下临时文件
+        assert!(!with_suffix(&path, ".tmp").exists());
+    }
+
+    #[test]
+    fn repeated_restores_walk_back() {
+        let dir = TempDir::new("restore");
+        let path = dir.join("config.json");
+        for content in ["v1", "v2", "v3"] {
+            save_with_backup(&path, content, 5).unwrap();
+        }
+
+        let first = restore_previous(&path, None).unwrap();
+        assert_eq!(read(&path), "v2");
+        assert_eq!(read(&first), "v2");
+
+        let second = restore_previous(&path, Some(&first)).unwrap();
+        assert_eq!(read(&path), "v1");
+        assert_eq!(read(&second), "v1");
+
+        // 没有更早的备份
+        let error = restore_previous(&path, Some(&second)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(read(&path), "v1");
+
+        // 被替换的 v3 已备份，没有重复的备份
+        let mut backups: Vec<String> = list_backups(&path).iter().map(|b| read(b)).collect();
+        backups.sort();
+        assert_eq!(backups, vec!["v1", "v2", "v3"]);
+    }
+
+    #[test]
+    fn restore_after_edit_starts_from_newest() {
+        let dir = TempDir::new("restore-edit");
+        let path = dir.join("config.json");
+        for content in ["v1", "v2", "v3"] {
+            save_with_backup(&path, content, 5).unwrap();
+        }
+        let first = restore_previous(&path, None).unwrap();
+        assert_eq!(read(&path), "v2");
+
+        // 恢复后在外部修改了配置文件，再次恢复时从最新的备份开始
+        fs::write(&path, "edited").unwrap();
+        restore_previous(&path, Some(&first)).unwrap();
+        assert_eq!(read(&path), "v3");
+    }
+}
@@ -0,0 +1,192 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::utils::jsonc;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+
+// 配置文件格式，由扩展名决定：.toml、.yaml/.yml，其余按 JSON（允许注释和末尾多余的逗号）处理。
+// 三种格式使用相同的结构，都可以写注释
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+// 解析失败的位置与原因
+#[derive(Clone, Debug)]
+pub struct FormatError {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub field: Option<String>, // 出错的字段，如 "oscillation_guard.window_secs"
+    pub message: String,
+}
+
+impl FormatError {
+    fn new(message: String) -> Self {
+        Self {
+            line: None,
+            column: None,
+            field: None,
+            message,
+        }
+    }
+
+    fn with_field(mut self, field: String) -> Self {
+        // serde_path_to_error 用 "." 表示顶层，用 "?" 表示未知位置
+        if field != "." && field != "?" {
+            self.field = Some(field);
+        }
+        self
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Json => write!(f, "JSON"),
+            ConfigFormat::Toml => write!(f, "TOML"),
+            ConfigFormat::Yaml => write!(f, "YAML"),
+        }
+    }
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    // 解析为通用的数据，供版本迁移使用
+    pub fn parse_value(self, content: &str) -> Result<Value, FormatError> {
+        self.deserialize(content)
+    }
+
+    // 直接解析原文，错误信息带有字段路径和行列号
+    pub fn deserialize<T: DeserializeOwned>(self, content: &str) -> Result<T, FormatError> {
+        match self {
+            ConfigFormat::Json => {
+                let content = jsonc::strip(content);
+                serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(&content))
+                    .map_err(|e| {
+                        let field = e.path().to_string();
+                        json_error(e.into_inner()).with_field(field)
+                    })
+            }
+            ConfigFormat::Toml => {
+                serde_path_to_error::deserialize(toml::Deserializer::new(content)).map_err(|e| {
+                    let field = e.path().to_string();
+                    toml_error(content, e.into_inner()).with_field(field)
+                })
+            }
+            // serde_yaml 要求枚举写成 "!SetMode EnglishOnly" 的形式，先转为通用数据，
+            // 使 {SetMode: EnglishOnly} 的写法与 JSON 一致（类型错误只能给出字段路径）
+            ConfigFormat::Yaml => {
+                let value = serde_yaml::from_str(content).map_err(yaml_error)?;
+                Self::deserialize_value(value)
+            }
+        }
+    }
+
+    // 从迁移后的数据解析，没有行列号
+    pub fn deserialize_value<T: DeserializeOwned>(value: Value) -> Result<T, FormatError> {
+        serde_path_to_error::deserialize(value).map_err(|e| {
+            let field = e.path().to_string();
+            FormatError::new(strip_position(&e.into_inner().to_string())).with_field(field)
+        })
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, String> {
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            // 经由通用数据转换，使枚举动作写成 {SetMode = "EnglishOnly"} 的形式，与 JSON 一致
+            ConfigFormat::Toml => {
+                let mut value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+                remove_nulls(&mut value); // TOML 没有空值，省略的字段按默认值处理
+                toml::to_string_pretty(&value).map_err(|e| e.to_string())
+            }
+            ConfigFormat::Yaml => {
+                let mut value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+                remove_nulls(&mut value);
+                serde_yaml::to_string(&value).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+fn json_error(e: serde_json::Error) -> FormatError {
+    // 行号为 0 表示错误与位置无关
+    let (line, column) = match e.line() {
+        0 => (None, None),
+        line => (Some(line), Some(e.column())),
+    };
+    FormatError {
+        line,
+        column,
+        field: None,
+        message: strip_position(&e.to_string()),
+    }
+}
+
+fn toml_error(content: &str, e: toml::de::Error) -> FormatError {
+    let (line, column) = match e.span() {
+        Some(span) => {
+            let (line, column) = line_column(content, span.start);
+            (Some(line), Some(column))
+        }
+        None => (None, None),
+    };
+    FormatError {
+        line,
+        column,
+        field: None,
+        message: e.message().to_string(),
+    }
+}
+
+fn yaml_error(e: serde_yaml::Error) -> FormatError {
+    let location = e.location();
+    FormatError {
+        line: location.as_ref().map(|location| location.line()),
+        column: location.as_ref().map(|location| location.column()),
+        field: None,
+        message: strip_position(&e.to_string()),
+    }
+}
+
+// 字节下标对应的行号和列号（从 1 开始，列按字符计数）
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+// 错误信息末尾带有 " at line x column y"，位置已单独记录
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message.to_string(),
+    }
+}
@@ -0,0 +1,254 @@
+use log::{error, info, warn};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 配置文件位置，按优先级依次为：
+// 1. 命令行参数 --config <路径>
+// 2. 环境变量 IME_CONTROLLER_CONFIG
+// 3. 便携模式：程序目录下存在 portable 标记文件时，使用程序目录
+// 4. 当前用户的配置目录（Windows 为 %APPDATA%\ime-controller，Linux 为 $XDG_CONFIG_HOME/ime-controller）
+// 5. 无法获取用户配置目录时，退回程序目录
+// 路径为已存在的目录时，使用其中的配置文件。目录中的配置文件按 CONFIG_FILE_NAMES 的顺序
+// 选择第一个存在的，都不存在时使用 config.json
+
+pub const CONFIG_FILE_NAME: &str = "config.json";
+pub const CONFIG_FILE_NAMES: &[&str] = &["config.json", "config.toml", "config.yaml", "config.yml"];
+pub const CONFIG_ENV_VAR: &str = "IME_CONTROLLER_CONFIG";
+pub const PORTABLE_MARKER: &str = "portable";
+const APP_DIR_NAME: &str = "ime-controller";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigSource {
+    Flag,
+    EnvVar,
+    Portable,
+    UserDir,
+    ExeDirFallback,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Flag => write!(f, "命令行参数 --config"),
+            ConfigSource::EnvVar => write!(f, "环境变量 {}", CONFIG_ENV_VAR),
+            ConfigSource::Portable => {
+                write!(f, "便携模式（程序目录下存在 {} 文件）", PORTABLE_MARKER)
+            }
+            ConfigSource::UserDir => write!(f, "用户配置目录"),
+            ConfigSource::ExeDirFallback => write!(f, "无法获取用户配置目录，使用程序目录"),
+        }
+    }
+}
+
+// 确定配置文件位置所需的外部信息
+pub struct LocationInputs {
+    pub args: Vec<OsString>,
+    pub env_value: Option<OsString>,
+    pub exe_dir: PathBuf,
+    pub user_config_dir: Option<PathBuf>,
+    pub portable_marker_exists: bool,
+}
+
+// 命令行中 --config <路径> 或 --config=<路径> 的值。
+// 缺少路径时返回错误，而不是改用其他位置的配置文件
+fn config_flag(args: &[OsString]) -> Result<Option<PathBuf>, String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = if arg == "--config" {
+            iter.next().cloned()
+        } else {
+            arg.to_string_lossy()
+                .strip_prefix("--config=")
+                .map(OsString::from)
+        };
+        match value {
+            Some(value) if !value.is_empty() => return Ok(Some(PathBuf::from(value))),
+            Some(_) => return Err("命令行参数 --config 的路径为空".to_string()),
+            None if arg == "--config" => {
+                return Err("命令行参数 --config 缺少路径，应为 --config <路径>".to_string())
+            }
+            None => {}
+        }
+    }
+    Ok(None)
+}
+
+// 返回配置文件或其所在目录（便携模式和用户配置目录返回目录）
+pub fn resolve(inputs: &LocationInputs) -> Result<(PathBuf, ConfigSource), String> {
+    if let Some(path) = config_flag(&inputs.args)? {
+        return Ok((path, ConfigSource::Flag));
+    }
+    if let Some(value) = inputs.env_value.as_ref().filter(|value| !value.is_empty()) {
+        return Ok((PathBuf::from(value), ConfigSource::EnvVar));
+    }
+    if inputs.portable_marker_exists {
+        return Ok((inputs.exe_dir.clone(), ConfigSource::Portable));
+    }
+    Ok(match &inputs.user_config_dir {
+        Some(dir) => (dir.join(APP_DIR_NAME), ConfigSource::UserDir),
+        None => (inputs.exe_dir.clone(), ConfigSource::ExeDirFallback),
+    })
+}
+
+// 目录中的配置文件，同时存在多种格式时使用优先的一种
+fn config_file_in(dir: &Path) -> PathBuf {
+    let existing: Vec<PathBuf> = CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .collect();
+    if existing.len() > 1 {
+        warn!(
+            "配置目录中有多个配置文件 {:?}，使用 {:?}",
+            existing, existing[0]
+        );
+    }
+    existing
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| dir.join(CONFIG_FILE_NAME))
+}
+
+// 确定本次运行使用的配置文件，并记录选择的原因。命令行参数有误时返回错误
+pub fn config_file_path() -> Result<PathBuf, String> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let inputs = LocationInputs {
+        args: std::env::args_os().skip(1).collect(),
+        env_value: std::env::var_os(CONFIG_ENV_VAR),
+        portable_marker_exists: exe_dir.join(PORTABLE_MARKER).exists(),
+        user_config_dir: dirs::config_dir(),
+        exe_dir,
+    };
+    let (path, source) = resolve(&inputs)?;
+    // 显式指定的路径可以是文件或已存在的目录，其余来源均为目录
+    let is_dir = !matches!(source, ConfigSource::Flag | ConfigSource::EnvVar) || path.is_dir();
+    let path = if is_dir { config_file_in(&path) } else { path };
+    info!("配置文件: {:?}（来源: {}）", path, source);
+
+    if let Some(dir) = path.parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            error!("无法创建配置目录 {:?}: {}", dir, e);
+        }
+    }
+    if source == ConfigSource::UserDir {
+        adopt_exe_local_config(&inputs.exe_dir.join(CONFIG_FILE_NAME), &path);
+    }
+    Ok(path)
+}
+
+// 旧版本的配置文件在程序目录下，首次使用用户配置目录时复制过来
+fn adopt_exe_local_config(legacy: &Path, path: &Path) {
+    if path.exists() || !legacy.exists() {
+        return;
+    }
+    match fs::copy(legacy, path) {
+        Ok(_) => info!("已将程序目录下的配置文件 {:?} 复制到 {:?}", legacy, path),
+        Err(e) => error!("复制程序目录下的配置文件 {:?} 失败: {}", legacy, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_util::TempDir;
+
+    fn inputs(args: &[&str]) -> LocationInputs {
+        LocationInputs {
+            args: args.iter().map(OsString::from).collect(),
+            env_value: Some(OsString::from("env.json")),
+            exe_dir: PathBuf::from("exe"),
+            user_config_dir: Some(PathBuf::from("appdata")),
+            portable_marker_exists: true,
+        }
+    }
+
+    #[test]
+    fn resolve_follows_precedence() {
+        let mut inputs = inputs(&["--config", "flag.json"]);
+        assert_eq!(
+            resolve(&inputs),
+            Ok((PathBuf::from("flag.json"), ConfigSource::Flag))
+        );
+        inputs.args.clear();
+        assert_eq!(
+            resolve(&inputs),
+            Ok((PathBuf::from("env.json"), ConfigSource::EnvVar))
+        );
+        inputs.env_value = None;
+        assert_eq!(
+            resolve(&inputs),
+            Ok((PathBuf::from("exe"), ConfigSource::Portable))
+        );
+        inputs.portable_marker_exists = false;
+        assert_eq!(
+            resolve(&inputs),
+            Ok((
+                PathBuf::from("appdata").join(APP_DIR_NAME),
+                ConfigSource::UserDir
+            ))
+        );
+        inputs.user_config_dir = None;
+        assert_eq!(
+            resolve(&inputs),
+            Ok((PathBuf::from("exe"), ConfigSource::ExeDirFallback))
+        );
+    }
+
+    #[test]
+    fn flag_accepts_equals_form() {
+        assert_eq!(
+            resolve(&inputs(&["--other", "--config=flag.toml"])),
+            Ok((PathBuf::from("flag.toml"), ConfigSource::Flag))
+        );
+    }
+
+    #[test]
+    fn empty_env_value_is_ignored() {
+        let mut inputs = inputs(&[]);
+        inputs.env_value = Some(OsString::new());
+        assert_eq!(
+            resolve(&inputs),
+            Ok((PathBuf::from("exe"), ConfigSource::Portable))
+        );
+    }
+
+    #[test]
+    fn flag_without_path_is_an_error() {
+        assert!(resolve(&inputs(&["--config"])).is_err());
+        assert!(resolve(&inputs(&["--config="])).is_err());
+        assert!(resolve(&inputs(&["--config", ""])).is_err());
+    }
+
+    #[test]
+    fn adopts_legacy_config_when_missing() {
+        let dir = TempDir::new("adopt");
+        let legacy = dir.join("legacy.json");
+        let path = dir.join("config.json");
+        fs::write(&legacy, "{\"mode\": 1}").unwrap();
+
+        adopt_exe_local_config(&legacy, &path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"mode\": 1}");
+    }
+
+    #[test]
+    fn does_not_overwrite_existing_config() {
+        let dir = TempDir::new("keep");
+        let legacy = dir.join("legacy.json");
+        let path = dir.join("config.json");
+        fs::write(&legacy, "{\"mode\": 1}").unwrap();
+        fs::write(&path, "{\"mode\": 2}").unwrap();
+
+        adopt_exe_local_config(&legacy, &path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"mode\": 2}");
+
+        // 旧配置文件不存在时不创建
+        let missing = dir.join("missing.json");
+        adopt_exe_local_config(&dir.join("none.json"), &missing);
+        assert!(!missing.exists());
+    }
+}
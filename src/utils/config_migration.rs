@@ -0,0 +1,189 @@
+use crate::utils::parse_hotkey::Hotkey;
+use serde_json::{Map, Value};
+
+// 配置文件格式的版本。修改字段名称或结构时加一，并在 MIGRATIONS 末尾添加对应的迁移
+pub const CURRENT_VERSION: u64 = 1;
+
+type Migration = fn(&mut Map<String, Value>, &mut Vec<String>);
+
+// MIGRATIONS[n] 将版本 n 的配置升级到版本 n + 1
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+pub struct MigrationReport {
+    pub from_version: u64,
+    pub changes: Vec<String>, // 每一项修改的说明，用于日志
+}
+
+// 将配置逐版本升级到 CURRENT_VERSION。没有 version 字段的配置视为版本 0；
+// 已是最新版本时返回 None，版本比程序支持的更新时返回错误（不做修改）
+pub fn migrate(value: &mut Value) -> Result<Option<MigrationReport>, String> {
+    let Some(object) = value.as_object_mut() else {
+        return Err("配置文件的顶层必须是对象".to_string());
+    };
+    let from_version = match object.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("version 必须是非负整数，实际为 {}", version))?,
+    };
+    if from_version > CURRENT_VERSION {
+        return Err(format!(
+            "配置文件版本 {} 比程序支持的版本 {} 更新",
+            from_version, CURRENT_VERSION
+        ));
+    }
+    if from_version == CURRENT_VERSION {
+        return Ok(None);
+    }
+
+    let mut changes = Vec::new();
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from_version as usize) {
+        let before = changes.len();
+        migration(object, &mut changes);
+        if changes.len() == before {
+            changes.push(format!("版本 {} -> {}: 无需修改", version, version + 1));
+        }
+    }
+    object.insert("version".to_string(), Value::from(CURRENT_VERSION));
+
+    Ok(Some(MigrationReport {
+        from_version,
+        changes,
+    }))
+}
+
+// 版本 0 -> 1：
+// - enabled 改名为 master_switch（两者都存在时以 master_switch 为准）
+// - hotkey_toggle / hotkey_switch_mode 迁移到 bindings
+fn migrate_v0_to_v1(object: &mut Map<String, Value>, changes: &mut Vec<String>) {
+    if let Some(enabled) = object.remove("enabled") {
+        if object.contains_key("master_switch") {
+            changes.push("删除已废弃的 enabled（以 master_switch 为准）".to_string());
+        } else {
+            changes.push(format!("enabled 改名为 master_switch: {}", enabled));
+            object.insert("master_switch".to_string(), enabled);
+        }
+    }
+
+    let legacy = [
+        ("hotkey_toggle", "ToggleMasterSwitch"),
+        ("hotkey_switch_mode", "CycleMode"),
+    ];
+    for (field, action) in legacy {
+        let Some(value) = object.remove(field) else {
+            continue;
+        };
+        let Some(hotkey) = value.as_str().and_then(|s| s.parse::<Hotkey>().ok()) else {
+            changes.push(format!("删除无效的 {}: {}", field, value));
+            continue;
+        };
+
+        let bindings = object
+            .entry("bindings")
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Some(bindings) = bindings.as_object_mut() else {
+            changes.push(format!("bindings 不是对象，删除 {}: {}", field, value));
+            continue;
+        };
+        let key = hotkey.to_string();
+        if bindings
+            .keys()
+            .any(|k| k.parse::<Hotkey>().ok() == Some(hotkey))
+        {
+            changes.push(format!("删除 {}: {} 已在 bindings 中绑定", field, key));
+        } else {
+            changes.push(format!(
+                "{} 迁移到 bindings: \"{}\": \"{}\"",
+                field, key, action
+            ));
+            bindings.insert(key, Value::from(action));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn v0_is_migrated_to_v1() {
+        let mut config = json!({
+            "autostart": true,
+            "enabled": false,
+            "hotkey_toggle": "alt+m",
+            "hotkey_switch_mode": "Ctrl+Shift+Space",
+            "ime_mode": "EnglishOnly",
+            "unknown": [1, 2]
+        });
+        let report = migrate(&mut config).unwrap().unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.changes.len(), 3);
+        assert_eq!(
+            config,
+            json!({
+                "version": 1,
+                "autostart": true,
+                "master_switch": false,
+                "bindings": {
+                    "Alt+M": "ToggleMasterSwitch",
+                    "Ctrl+Shift+Space": "CycleMode"
+                },
+                "ime_mode": "EnglishOnly",
+                "unknown": [1, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn v0_keeps_existing_bindings_and_master_switch() {
+        let mut config = json!({
+            "enabled": false,
+            "master_switch": true,
+            "hotkey_toggle": "Alt+M",
+            "hotkey_switch_mode": "not a hotkey",
+            "bindings": {"alt+M": "CycleMode"}
+        });
+        migrate(&mut config).unwrap().unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "version": 1,
+                "master_switch": true,
+                "bindings": {"alt+M": "CycleMode"}
+            })
+        );
+    }
+
+    #[test]
+    fn v0_without_legacy_fields_only_gains_version() {
+        let mut config = json!({"autostart": false});
+        let report = migrate(&mut config).unwrap().unwrap();
+        assert_eq!(report.changes, vec!["版本 0 -> 1: 无需修改"]);
+        assert_eq!(config, json!({"version": 1, "autostart": false}));
+    }
+
+    #[test]
+    fn v1_is_unchanged() {
+        let original = json!({
+            "version": 1,
+            "enabled": true,
+            "hotkey_toggle": "Alt+M",
+            "bindings": {"Alt+S": "CycleMode"}
+        });
+        let mut config = original.clone();
+        assert!(migrate(&mut config).unwrap().is_none());
+        assert_eq!(config, original);
+    }
+
+    #[test]
+    fn invalid_versions_are_rejected() {
+        for version in [json!(2), json!(-1), json!("1")] {
+            let mut config = json!({"version": version});
+            let original = config.clone();
+            assert!(migrate(&mut config).is_err(), "{}", version);
+            assert_eq!(config, original);
+        }
+        assert!(migrate(&mut json!([])).is_err());
+    }
+}
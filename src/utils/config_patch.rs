@@ -0,0 +1,562 @@
+use crate::utils::config_format::ConfigFormat;
+use crate::utils::jsonc;
+use crate::utils::trigger::Trigger;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+// 保存配置时只修改有变化的字段，注释、字段顺序和程序不认识的字段原样保留。
+// 比较的是修改前后的配置（而不是文件本身），文件中缺省的字段没有变化时不会被写入。
+// 无法在原文上修改时（如 YAML 中修改列表）返回错误，由调用方改为重写整个文件
+
+enum Change {
+    Set(Vec<String>, Value),
+    Remove(Vec<String>),
+}
+
+// 逐层比较两份配置，列表整体比较
+fn diff(old: &Value, new: &Value, path: &mut Vec<String>, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, new_value) in new {
+                path.push(key.clone());
+                match old.get(key) {
+                    Some(old_value) => diff(old_value, new_value, path, changes),
+                    None if new_value.is_null() => {}
+                    None => changes.push(Change::Set(path.clone(), new_value.clone())),
+                }
+                path.pop();
+            }
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                path.push(key.clone());
+                changes.push(Change::Remove(path.clone()));
+                path.pop();
+            }
+        }
+        _ if old == new => {}
+        // 空值表示字段被清除，删除后按默认值处理
+        (_, Value::Null) => changes.push(Change::Remove(path.clone())),
+        _ => changes.push(Change::Set(path.clone(), new.clone())),
+    }
+}
+
+// 把从 old 到 new 的修改应用到配置文件的原文上
+pub fn patch<T: Serialize + DeserializeOwned>(
+    format: ConfigFormat,
+    content: &str,
+    old: &T,
+    new: &T,
+) -> Result<String, String> {
+    let new_value = serde_json::to_value(new).map_err(|e| e.to_string())?;
+    let old_value = serde_json::to_value(old).map_err(|e| e.to_string())?;
+    let mut changes = Vec::new();
+    diff(&old_value, &new_value, &mut Vec::new(), &mut changes);
+    if changes.is_empty() {
+        return Ok(content.to_string());
+    }
+    let written = format.parse_value(content).map_err(|e| e.message)?;
+    for change in &mut changes {
+        let (Change::Set(path, _) | Change::Remove(path)) = change;
+        spell_as_written(&written, path);
+    }
+
+    let patched = match format {
+        ConfigFormat::Json => patch_json(content, &changes)?,
+        ConfigFormat::Toml => patch_toml(content, &changes)?,
+        ConfigFormat::Yaml => patch_yaml(content, &changes)?,
+    };
+
+    // 修改后的文件必须能读回相同的配置
+    let reparsed: T = format.deserialize(&patched).map_err(|e| e.message)?;
+    if serde_json::to_value(&reparsed).map_err(|e| e.to_string())? != new_value {
+        return Err("修改后的文件与配置不一致".to_string());
+    }
+    Ok(patched)
+}
+
+// 文件中的字段名可能与序列化出的写法不同（如绑定表中的 "alt+m" 与规范写法 "Alt+M"），
+// 修改已有字段时沿用文件中的写法
+fn spell_as_written(written: &Value, path: &mut [String]) {
+    let mut current = written;
+    for depth in 0..path.len() {
+        let Value::Object(object) = current else {
+            return;
+        };
+        let parent = depth.checked_sub(1).map(|i| path[i].as_str());
+        let key = &path[depth];
+        let Some((spelled, value)) = object.get_key_value(key).or_else(|| {
+            object
+                .iter()
+                .find(|(spelled, _)| same_key(parent, spelled, key))
+        }) else {
+            return;
+        };
+        path[depth] = spelled.clone();
+        current = value;
+    }
+}
+
+// 绑定表的字段名按解析后的触发方式比较
+fn same_key(parent: Option<&str>, spelled: &str, key: &str) -> bool {
+    if parent != Some("bindings") {
+        return false;
+    }
+    match (spelled.parse::<Trigger>(), key.parse::<Trigger>()) {
+        (Ok(spelled), Ok(key)) => spelled == key,
+        _ => false,
+    }
+}
+
+fn patch_json(content: &str, changes: &[Change]) -> Result<String, String> {
+    let mut document = jsonc::Document::new(content);
+    for change in changes {
+        match change {
+            Change::Set(path, value) => document.set(path, value)?,
+            Change::Remove(path) => document.remove(path)?,
+        }
+    }
+    Ok(document.into_string())
+}
+
+fn patch_toml(content: &str, changes: &[Change]) -> Result<String, String> {
+    let mut document: toml_edit::DocumentMut = content.parse().map_err(|e| format!("{}", e))?;
+    for change in changes {
+        match change {
+            Change::Set(path, value) => set_toml(document.as_table_mut(), path, value)?,
+            Change::Remove(path) => remove_toml(document.as_table_mut(), path),
+        }
+    }
+    Ok(document.to_string())
+}
+
+fn set_toml(
+    table: &mut dyn toml_edit::TableLike,
+    path: &[String],
+    value: &Value,
+) -> Result<(), String> {
+    let (key, rest) = path.split_first().ok_or("路径为空")?;
+    if !rest.is_empty() {
+        if let Some(inner) = table.get_mut(key).and_then(|item| item.as_table_like_mut()) {
+            return set_toml(inner, rest, value);
+        }
+    }
+    let nested = rest.iter().rev().fold(value.clone(), |value, key| {
+        Value::Object(serde_json::Map::from_iter([(key.clone(), value)]))
+    });
+    let new = toml_value(&nested)?;
+    match table.get_mut(key) {
+        // 保留原值前后的空白和行尾注释
+        Some(toml_edit::Item::Value(old)) => {
+            let decor = old.decor().clone();
+            *old = new;
+            *old.decor_mut() = decor;
+        }
+        Some(item) => *item = toml_edit::Item::Value(new),
+        None => {
+            table.insert(key, toml_edit::Item::Value(new));
+        }
+    }
+    Ok(())
+}
+
+fn remove_toml(table: &mut dyn toml_edit::TableLike, path: &[String]) {
+    match path {
+        [key] => {
+            table.remove(key);
+        }
+        [key, rest @ ..] => {
+            if let Some(inner) = table.get_mut(key).and_then(|item| item.as_table_like_mut()) {
+                remove_toml(inner, rest);
+            }
+        }
+        [] => {}
+    }
+}
+
+fn toml_value(value: &Value) -> Result<toml_edit::Value, String> {
+    Ok(match value {
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().ok_or("数值超出范围")?.into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(toml_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            toml_edit::Value::Array(items.into_iter().collect())
+        }
+        Value::Object(map) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, value) in map.iter().filter(|(_, value)| !value.is_null()) {
+                table.insert(key, toml_value(value)?);
+            }
+            toml_edit::Value::InlineTable(table)
+        }
+        Value::Null => return Err("TOML 不支持空值".to_string()),
+    })
+}
+
+// YAML 按行修改：只支持块状映射中写在同一行的单个值，
+// 修改已有的值、在已有的映射末尾添加或删除这样的字段
+fn patch_yaml(content: &str, changes: &[Change]) -> Result<String, String> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    for change in changes {
+        match change {
+            Change::Set(path, value) => set_yaml(&mut lines, path, value)?,
+            Change::Remove(path) => {
+                if let Some(index) = find_yaml_key(&lines, path)?.found {
+                    if yaml_inline_value(&lines[index]).is_none() {
+                        return Err(format!("YAML 中无法删除多行的字段 {}", path.join(".")));
+                    }
+                    lines.remove(index);
+                }
+            }
+        }
+    }
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut patched = lines.join(newline);
+    if content.ends_with('\n') {
+        patched.push_str(newline);
+    }
+    Ok(patched)
+}
+
+fn set_yaml(lines: &mut Vec<String>, path: &[String], value: &Value) -> Result<(), String> {
+    if value.is_object() || value.is_array() {
+        return Err(format!("YAML 中只能修改单个值，{} 不是", path.join(".")));
+    }
+    let rendered = yaml_scalar(value)?;
+    let location = find_yaml_key(lines, path)?;
+    match location.found {
+        Some(index) => {
+            let line = &lines[index];
+            let (start, end) = yaml_inline_value(line)
+                .ok_or_else(|| format!("YAML 中 {} 的值不在同一行", path.join(".")))?;
+            lines[index] = format!("{}{}{}", &line[..start], rendered, &line[end..]);
+        }
+        None if location.missing_depth + 1 == path.len() => {
+            let key = yaml_scalar(&Value::String(path[path.len() - 1].clone()))?;
+            let line = format!("{}{}: {}", location.indent, key, rendered);
+            lines.insert(location.insert_at, line);
+        }
+        None => return Err(format!("YAML 中缺少 {} 的上层映射", path.join("."))),
+    }
+    Ok(())
+}
+
+struct YamlLocation {
+    found: Option<usize>,
+    // 未找到时：缺少的是第几层、应插入的行及缩进
+    missing_depth: usize,
+    insert_at: usize,
+    indent: String,
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#') && trimmed != "---"
+}
+
+// 逐层查找字段所在的行，每一层的范围是上一层字段之后缩进更深的行
+fn find_yaml_key(lines: &[String], path: &[String]) -> Result<YamlLocation, String> {
+    let (mut start, mut end) = (0, lines.len());
+    for (depth, key) in path.iter().enumerate() {
+        let indent = lines[start..end]
+            .iter()
+            .find(|line| is_content(line))
+            .map(|line| indent_of(line));
+        let Some(indent) = indent else {
+            return Err(format!("YAML 中 {} 的上层映射为空", path.join(".")));
+        };
+        let found = (start..end).find(|&index| {
+            is_content(&lines[index])
+                && indent_of(&lines[index]) == indent
+                && yaml_key(&lines[index]).as_deref() == Some(key.as_str())
+        });
+        let Some(index) = found else {
+            // 插入到本层最后一个字段（及其下层内容）之后
+            let last = (start..end).rev().find(|&index| is_content(&lines[index]));
+            return Ok(YamlLocation {
+                found: None,
+                missing_depth: depth,
+                insert_at: last.map_or(start, |index| index + 1),
+                indent: " ".repeat(indent),
+            });
+        };
+        if depth + 1 == path.len() {
+            return Ok(YamlLocation {
+                found: Some(index),
+                missing_depth: depth,
+                insert_at: index,
+                indent: String::new(),
+            });
+        }
+        start = index + 1;
+        end = (start..end)
+            .find(|&i| is_content(&lines[i]) && indent_of(&lines[i]) <= indent)
+            .unwrap_or(end);
+    }
+    Err("路径为空".to_string())
+}
+
+// 行中字段名的结束位置（冒号之前），不是 "字段: 值" 形式时返回 None
+fn yaml_key_end(line: &str) -> Option<usize> {
+    let trimmed_start = indent_of(line);
+    let rest = &line[trimmed_start..];
+    if rest.starts_with("- ") || rest == "-" {
+        return None;
+    }
+    let key_end = match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let mut escaped = false;
+            let close = rest[1..].char_indices().find(|&(_, c)| {
+                let is_close = c == quote && !escaped;
+                escaped = quote == '"' && c == '\\' && !escaped;
+                is_close
+            })?;
+            1 + close.0 + 1
+        }
+        _ => rest
+            .match_indices(':')
+            .map(|(index, _)| index)
+            .find(|&index| rest[index + 1..].is_empty() || rest[index + 1..].starts_with(' '))?,
+    };
+    rest[key_end..]
+        .starts_with(':')
+        .then_some(trimmed_start + key_end)
+}
+
+fn yaml_key(line: &str) -> Option<String> {
+    let key_end = yaml_key_end(line)?;
+    let key = line[..key_end].trim();
+    match serde_yaml::from_str::<serde_yaml::Value>(key).ok()? {
+        serde_yaml::Value::String(key) => Some(key),
+        serde_yaml::Value::Null => None,
+        other => serde_yaml::to_string(&other)
+            .ok()
+            .map(|s| s.trim_end().to_string()),
+    }
+}
+
+// 同一行中值的位置（不含行尾注释）。值为空、多行文本、锚点或流式集合时返回 None
+fn yaml_inline_value(line: &str) -> Option<(usize, usize)> {
+    let colon = yaml_key_end(line)?;
+    let after = colon + 1;
+    let start = after + (line[after..].len() - line[after..].trim_start().len());
+    let value = &line[start..];
+    if value.is_empty() || value.starts_with(['#', '|', '>', '&', '*', '!', '{', '[']) {
+        return None;
+    }
+    let end = match value.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let mut escaped = false;
+            let close = value[1..].char_indices().find(|&(_, c)| {
+                let is_close = c == quote && !escaped;
+                escaped = quote == '"' && c == '\\' && !escaped;
+                is_close
+            })?;
+            start + 1 + close.0 + 1
+        }
+        _ => start + value.find(" #").unwrap_or(value.len()),
+    };
+    Some((start, line[..end].trim_end().len()))
+}
+
+fn yaml_scalar(value: &Value) -> Result<String, String> {
+    serde_yaml::to_string(value)
+        .map(|s| s.trim_end().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Clone, Default)]
+    struct Settings {
+        mode: u32,
+        #[serde(default)]
+        bindings: BTreeMap<Trigger, String>,
+    }
+
+    fn settings(mode: u32, bindings: &[(&str, &str)]) -> Settings {
+        Settings {
+            mode,
+            bindings: bindings
+                .iter()
+                .map(|(key, value)| (key.parse().unwrap(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    const JSON: &str = r#"{
+  // 程序不认识的字段也要保留
+  "enabled": true,
+  "mode": 1, // 模式
+  "bindings": {
+    "Ctrl+Space": "ToggleMasterSwitch", // 切换
+  },
+}
+"#;
+
+    const YAML: &str = "# 配置\nenabled: true  # 旧字段\nmode: 1  # 模式\nbindings:  # 快捷键\n  Ctrl+Space: ToggleMasterSwitch  # 切换\n";
+
+    #[test]
+    fn unchanged_config_is_returned_as_is() {
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        assert_eq!(patch(ConfigFormat::Json, JSON, &old, &old).unwrap(), JSON);
+    }
+
+    #[test]
+    fn json_scalar_change() {
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        let new = settings(2, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        assert_eq!(
+            patch(ConfigFormat::Json, JSON, &old, &new).unwrap(),
+            JSON.replace("\"mode\": 1,", "\"mode\": 2,")
+        );
+    }
+
+    #[test]
+    fn json_map_insert_and_removal() {
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        let new = settings(
+            1,
+            &[
+                ("Ctrl+Space", "ToggleMasterSwitch"),
+                ("Ctrl+K", "CycleMode"),
+            ],
+        );
+        let inserted = patch(ConfigFormat::Json, JSON, &old, &new).unwrap();
+        assert_eq!(
+            inserted,
+            JSON.replace("// 切换\n", "// 切换\n    \"Ctrl+K\": \"CycleMode\",\n")
+        );
+        assert_eq!(
+            patch(ConfigFormat::Json, &inserted, &new, &old).unwrap(),
+            JSON
+        );
+    }
+
+    #[test]
+    fn json_crlf() {
+        let content = JSON.replace('\n', "\r\n");
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        let new = settings(3, &[]);
+        assert_eq!(
+            patch(ConfigFormat::Json, &content, &old, &new).unwrap(),
+            "{\r\n  // 程序不认识的字段也要保留\r\n  \"enabled\": true,\r\n  \"mode\": 3, // 模式\r\n  \"bindings\": {\r\n  },\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn yaml_keeps_inline_comments() {
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        let new = settings(
+            2,
+            &[
+                ("Ctrl+Space", "CycleMode"),
+                ("Ctrl+K", "ToggleMasterSwitch"),
+            ],
+        );
+        let patched = patch(ConfigFormat::Yaml, YAML, &old, &new).unwrap();
+        assert_eq!(
+            patched,
+            "# 配置\nenabled: true  # 旧字段\nmode: 2  # 模式\nbindings:  # 快捷键\n  Ctrl+Space: CycleMode  # 切换\n  Ctrl+K: ToggleMasterSwitch\n"
+        );
+
+        let removed = patch(
+            ConfigFormat::Yaml,
+            &patched,
+            &new,
+            &settings(2, &[("Ctrl+Space", "CycleMode")]),
+        )
+        .unwrap();
+        assert_eq!(
+            removed,
+            "# 配置\nenabled: true  # 旧字段\nmode: 2  # 模式\nbindings:  # 快捷键\n  Ctrl+Space: CycleMode  # 切换\n"
+        );
+    }
+
+    #[test]
+    fn yaml_crlf() {
+        let content = YAML.replace('\n', "\r\n");
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        let new = settings(2, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        assert_eq!(
+            patch(ConfigFormat::Yaml, &content, &old, &new).unwrap(),
+            content.replace("mode: 1", "mode: 2")
+        );
+    }
+
+    #[test]
+    fn toml_keeps_comments() {
+        let content = "# 配置\nenabled = true\nmode = 1 # 模式\n\n[bindings]\n\"Ctrl+Space\" = \"ToggleMasterSwitch\" # 切换\n";
+        let old = settings(1, &[("Ctrl+Space", "ToggleMasterSwitch")]);
+        let new = settings(
+            2,
+            &[
+                ("Ctrl+Space", "ToggleMasterSwitch"),
+                ("Ctrl+K", "CycleMode"),
+            ],
+        );
+        assert_eq!(
+            patch(ConfigFormat::Toml, content, &old, &new).unwrap(),
+            "# 配置\nenabled = true\nmode = 2 # 模式\n\n[bindings]\n\"Ctrl+Space\" = \"ToggleMasterSwitch\" # 切换\n\"Ctrl+K\" = \"CycleMode\"\n"
+        );
+    }
+
+    #[test]
+    fn bindings_written_in_another_spelling() {
+        let json = "{\n  \"mode\": 1,\n  \"bindings\": {\n    \"alt + m\": \"ToggleMasterSwitch\", // 总开关\n    \"Ctrl+Space\": \"CycleMode\"\n  }\n}\n";
+        let old = settings(
+            1,
+            &[("Alt+M", "ToggleMasterSwitch"), ("Ctrl+Space", "CycleMode")],
+        );
+
+        // 修改动作时沿用文件中的写法
+        let changed = settings(1, &[("Alt+M", "ClearProfile"), ("Ctrl+Space", "CycleMode")]);
+        assert_eq!(
+            patch(ConfigFormat::Json, json, &old, &changed).unwrap(),
+            json.replace("\"ToggleMasterSwitch\"", "\"ClearProfile\"")
+        );
+
+        // 改用替代热键：删除原来写法的字段，添加规范写法的字段
+        let moved = settings(
+            1,
+            &[("Alt+N", "ToggleMasterSwitch"), ("Ctrl+Space", "CycleMode")],
+        );
+        assert_eq!(
+            patch(ConfigFormat::Json, json, &old, &moved).unwrap(),
+            "{\n  \"mode\": 1,\n  \"bindings\": {\n    \"Ctrl+Space\": \"CycleMode\",\n    \"Alt+N\": \"ToggleMasterSwitch\"\n  }\n}\n"
+        );
+
+        let yaml = "bindings:\n  alt+m: ToggleMasterSwitch  # 总开关\n";
+        let old = settings(1, &[("Alt+M", "ToggleMasterSwitch")]);
+        let new = settings(1, &[("Alt+M", "ClearProfile")]);
+        let content = format!("mode: 1\n{}", yaml);
+        assert_eq!(
+            patch(ConfigFormat::Yaml, &content, &old, &new).unwrap(),
+            content.replace("ToggleMasterSwitch", "ClearProfile")
+        );
+
+        let toml = "mode = 1\n\n[bindings]\n\"alt+m\" = \"ToggleMasterSwitch\" # 总开关\n";
+        assert_eq!(
+            patch(ConfigFormat::Toml, toml, &old, &new).unwrap(),
+            toml.replace("ToggleMasterSwitch", "ClearProfile")
+        );
+    }
+}
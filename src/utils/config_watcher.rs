@@ -0,0 +1,76 @@
+use crate::config::Config;
+use crate::constants;
+use crate::tray::main_window;
+use log::{error, info, warn};
+use notify::{Event, RecursiveMode, Watcher};
+use std::ffi::OsStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+// 监视配置文件所在的目录（编辑器常先写临时文件再重命名替换，只监视文件本身会丢失后续变化），
+// 配置文件变化时通知主窗口重新加载。编辑器保存时往往连续产生多个事件，
+// 最后一个事件之后 DEBOUNCE_MS 内没有新事件才通知
+const DEBOUNCE_MS: u64 = 300;
+
+pub fn start_config_watcher() {
+    let path = Config::get_config_path();
+    thread::spawn(move || {
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("无法监视配置文件: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            error!("无法监视配置目录 {:?}: {}", dir, e);
+            return;
+        }
+        info!("开始监视配置文件: {:?}", path);
+
+        while let Ok(event) = rx.recv() {
+            if !concerns_file(&event, file_name) {
+                continue;
+            }
+            loop {
+                match rx.recv_timeout(Duration::from_millis(DEBOUNCE_MS)) {
+                    Ok(_) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            unsafe {
+                let _ = PostMessageW(
+                    Some(main_window()),
+                    constants::WM_CONFIG_CHANGED,
+                    WPARAM(0),
+                    LPARAM(0),
+                );
+            }
+        }
+    });
+}
+
+// 事件是否修改了配置文件（忽略只读访问）
+fn concerns_file(event: &notify::Result<Event>, file_name: &OsStr) -> bool {
+    match event {
+        Ok(event) => {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == Some(file_name))
+        }
+        Err(e) => {
+            warn!("监视配置文件出错: {}", e);
+            false
+        }
+    }
+}
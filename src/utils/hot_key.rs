@@ -1,85 +1,510 @@
-use crate::config;
+use crate::config::{self, Action, CapsLockMode};
 use crate::constants;
-use crate::utils::parse_hotkey::parse_hotkey;
-use log::{error, info};
-use std::time::Duration;
+use crate::hooks::keyboard_hook::{is_capslock, set_hook_bindings, HookSettings};
+use crate::hooks::mouse_hook::set_mouse_bindings;
+use crate::hooks::suspend::is_suspended;
+use crate::tray::notifications::show_balloon_tip;
+use crate::utils::hotkey_alternatives::rank_alternatives;
+use crate::utils::parse_hotkey::Hotkey;
+use crate::utils::trigger::Trigger;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
 use windows::Win32::Foundation::{GetLastError, HWND};
 use windows::Win32::UI::Input::KeyboardAndMouse::*;
+use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
 
-// 注册所有热键，失败时重试
+// 每个冲突热键最多提供的替代组合数
+pub const MAX_ALTERNATIVES: usize = 3;
+
+// 被其他程序占用的热键及可用的替代组合（已按推荐程度排序）
+#[derive(Clone, Debug)]
+pub struct HotkeyConflict {
+    pub hotkey: Hotkey,
+    pub action: Action,
+    pub alternatives: Vec<Hotkey>,
+}
+
+// 绑定的注册状态
+#[derive(Clone, PartialEq, Debug)]
+pub enum HotkeyState {
+    Registered,
+    Hooked, // 由键盘或鼠标钩子处理，无需注册
+    Paused, // 游戏模式中暂不注册
+    Retrying { attempt: u32, reason: String },
+    Failed { reason: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct BindingStatus {
+    pub id: i32,
+    pub trigger: Trigger,
+    pub action: Action,
+    pub state: HotkeyState,
+}
+
+impl BindingStatus {
+    // 因热键已被其他程序占用而注册失败
+    fn is_conflict(&self) -> bool {
+        matches!(&self.state, HotkeyState::Failed { reason } if reason == REASON_ALREADY_REGISTERED)
+    }
+}
+
+impl fmt::Display for HotkeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HotkeyState::Registered => write!(f, "已注册"),
+            HotkeyState::Hooked => write!(f, "由钩子处理"),
+            HotkeyState::Paused => write!(f, "已暂停"),
+            HotkeyState::Retrying { attempt, reason } if reason.is_empty() => {
+                write!(f, "注册中 (第 {} 次)", attempt + 1)
+            }
+            HotkeyState::Retrying { reason, .. } => write!(f, "重试中: {}", reason),
+            HotkeyState::Failed { reason } => write!(f, "失败: {}", reason),
+        }
+    }
+}
+
+// 注册失败后的最大重试次数，第 n 次重试前等待 RETRY_BASE_DELAY_MS * 2^n 毫秒
+const MAX_RETRIES: u32 = 4;
+const RETRY_BASE_DELAY_MS: u32 = 1000;
+
+lazy_static! {
+    // 各绑定的注册状态，按 bindings 顺序
+    static ref HOTKEY_STATUSES: Mutex<Vec<BindingStatus>> = Mutex::new(Vec::new());
+    // 已注册的热键ID -> 动作；需同时持有时先锁 HOTKEY_STATUSES
+    static ref HOTKEY_ACTIONS: Mutex<HashMap<i32, Action>> = Mutex::new(HashMap::new());
+    // 最近一次注册时发现的冲突
+    static ref HOTKEY_CONFLICTS: Mutex<Vec<HotkeyConflict>> = Mutex::new(Vec::new());
+}
+
+// 根据热键ID查找绑定的动作
+pub fn action_for_hotkey_id(id: i32) -> Option<Action> {
+    HOTKEY_ACTIONS.lock().unwrap().get(&id).cloned()
+}
+
+// 注册所有热键。注册失败的热键由定时器按指数退避重试，不阻塞消息循环
 pub fn register_all_hotkeys(hwnd: HWND) {
-    register_all_hotkeys_with_retry(hwnd, 3);
+    unregister_all_hotkeys(hwnd);
+    update_hook_bindings();
+
+    let suspended = is_suspended();
+    *HOTKEY_STATUSES.lock().unwrap() = initial_statuses(suspended);
+
+    // 游戏模式下不注册，退出时会重新注册
+    if suspended {
+        info!("游戏模式中，暂不注册热键");
+        return;
+    }
+    register_pending_hotkeys(hwnd);
+}
+
+// 配置变化后只重新注册有变化的热键：未变化的保持注册，删除的注销，新增的注册
+pub fn refresh_hotkeys(hwnd: HWND) {
+    if is_suspended() {
+        register_all_hotkeys(hwnd);
+        return;
+    }
+    update_hook_bindings();
+
+    let mut new_statuses = initial_statuses(false);
+    let mut statuses = HOTKEY_STATUSES.lock().unwrap();
+    let mut actions = HOTKEY_ACTIONS.lock().unwrap();
+
+    // 保留触发方式未变化的热键及其ID和状态，动作可能已修改
+    let mut kept_old = vec![false; statuses.len()];
+    let mut kept_new = vec![false; new_statuses.len()];
+    for (new, kept_new) in new_statuses.iter_mut().zip(kept_new.iter_mut()) {
+        if new.state == HotkeyState::Hooked {
+            continue;
+        }
+        let Some((index, old)) = statuses.iter().enumerate().find(|(_, old)| {
+            old.trigger == new.trigger
+                && !matches!(old.state, HotkeyState::Hooked | HotkeyState::Paused)
+        }) else {
+            continue;
+        };
+        new.id = old.id;
+        new.state = old.state.clone();
+        if old.state == HotkeyState::Registered {
+            actions.insert(old.id, new.action.clone());
+        }
+        kept_old[index] = true;
+        *kept_new = true;
+    }
+
+    // 注销已删除或改为由钩子处理的热键
+    for (old, kept) in statuses.iter().zip(&kept_old) {
+        if !kept && old.state == HotkeyState::Registered {
+            info!("注销热键: {} -> {}", old.trigger, old.action.description());
+            unregister_hotkey(hwnd, old.id);
+            actions.remove(&old.id);
+        }
+    }
+
+    // 新增的热键使用未被占用的ID
+    let mut used_ids: Vec<i32> = new_statuses
+        .iter()
+        .zip(&kept_new)
+        .filter(|(_, kept)| **kept)
+        .map(|(status, _)| status.id)
+        .collect();
+    let mut next_id = constants::HOT_KEY_ID_BASE;
+    for (new, kept) in new_statuses.iter_mut().zip(&kept_new) {
+        if *kept || new.state == HotkeyState::Hooked {
+            continue;
+        }
+        while used_ids.contains(&next_id) {
+            next_id += 1;
+        }
+        new.id = next_id;
+        used_ids.push(next_id);
+    }
+
+    *statuses = new_statuses;
+    drop(actions);
+    drop(statuses);
+
+    unsafe {
+        let _ = KillTimer(Some(hwnd), constants::TIMER_ID_HOTKEY_RETRY);
+    }
+    register_pending_hotkeys(hwnd);
 }
 
-// 注册所有热键，失败时重试
-fn register_all_hotkeys_with_retry(hwnd: HWND, max_retries: i32) {
+// RegisterHotKey 无法表达的触发方式交给键盘钩子，鼠标按键交给鼠标钩子
+fn update_hook_bindings() {
     let config = config::CONFIG.read().unwrap();
-    let toggle_hotkey = config.hotkey_toggle.clone();
-    let switch_hotkey = config.hotkey_switch_mode.clone();
+    let (mouse_bindings, hook_bindings): (Vec<_>, Vec<_>) = config
+        .bindings()
+        .iter()
+        .filter(|(trigger, _)| !is_registrable(trigger, config.capslock_mode))
+        .map(|(trigger, action)| (*trigger, action.clone()))
+        .partition(|(trigger, _)| matches!(trigger, Trigger::Mouse { .. }));
+    let settings = HookSettings {
+        tap_timeout_ms: config.tap_timeout_ms,
+        double_tap_interval_ms: config.double_tap_interval_ms,
+        chord_timeout_ms: config.chord_timeout_ms,
+        capslock_mode: config.capslock_mode,
+        capslock_long_press_ms: config.capslock_long_press_ms,
+    };
     drop(config);
+    set_hook_bindings(hook_bindings, &settings);
+    set_mouse_bindings(mouse_bindings);
+}
 
-    let mut success_count = 0;
-    let mut total_count = 0;
-
-    // 注册切换总开关热键
-    if let Some(hotkey_str) = &toggle_hotkey {
-        total_count += 1;
-        if let Some((modifiers, key)) = parse_hotkey(hotkey_str) {
-            if register_hotkey(hwnd, constants::HOT_KEY_TOGGLE_ID, modifiers, key) {
-                info!("注册切换总开关热键: {}", hotkey_str);
-                success_count += 1;
+// 按当前配置生成各绑定的初始状态，热键ID按 bindings 顺序动态分配
+fn initial_statuses(suspended: bool) -> Vec<BindingStatus> {
+    let config = config::CONFIG.read().unwrap();
+    config
+        .bindings()
+        .iter()
+        .enumerate()
+        .map(|(index, (trigger, action))| BindingStatus {
+            id: constants::HOT_KEY_ID_BASE + index as i32,
+            trigger: *trigger,
+            action: action.clone(),
+            state: if !is_registrable(trigger, config.capslock_mode) {
+                HotkeyState::Hooked
+            } else if suspended {
+                HotkeyState::Paused
             } else {
-                error!("注册失败: {} (will retry)", hotkey_str);
-            }
-        } else {
-            error!("toggle_hotkey无效的热键格式: {}", hotkey_str);
+                HotkeyState::Retrying {
+                    attempt: 0,
+                    reason: String::new(),
+                }
+            },
+        })
+        .collect()
+}
+
+// 能否通过 RegisterHotKey 注册
+fn is_registrable(trigger: &Trigger, capslock_mode: CapsLockMode) -> bool {
+    matches!(trigger, Trigger::Hotkey(hotkey)
+        if !hotkey.is_side_specific() && !is_capslock(hotkey, capslock_mode))
+}
+
+// 第 attempt 次重试前的等待时间（毫秒），超过最大重试次数时返回 None
+fn retry_delay_ms(attempt: u32) -> Option<u32> {
+    (attempt < MAX_RETRIES).then(|| RETRY_BASE_DELAY_MS << attempt)
+}
+
+// 重试定时器到期
+pub fn retry_hotkey_registration(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(Some(hwnd), constants::TIMER_ID_HOTKEY_RETRY);
+    }
+    register_pending_hotkeys(hwnd);
+}
+
+// 立即重试所有注册失败的热键（托盘菜单），重新开始退避计数
+pub fn retry_failed_hotkeys(hwnd: HWND) {
+    if is_suspended() {
+        return;
+    }
+    unsafe {
+        let _ = KillTimer(Some(hwnd), constants::TIMER_ID_HOTKEY_RETRY);
+    }
+    for status in HOTKEY_STATUSES.lock().unwrap().iter_mut() {
+        if let HotkeyState::Failed { reason } = &status.state {
+            status.state = HotkeyState::Retrying {
+                attempt: 0,
+                reason: reason.clone(),
+            };
         }
     }
+    register_pending_hotkeys(hwnd);
+}
 
-    // 注册切换到中英文模式热键
-    if let Some(hotkey_str) = &switch_hotkey {
-        total_count += 1;
-        if let Some((modifiers, key)) = parse_hotkey(hotkey_str) {
-            if register_hotkey(hwnd, constants::HOT_KEY_SWITCH_MODE_ID, modifiers, key) {
-                info!("注册语言切换热键成功: {}", hotkey_str);
-                success_count += 1;
-            } else {
-                error!("注册语言切换热键失败: {} (will retry)", hotkey_str);
+// 注册所有待重试的热键；仍有失败时启动重试定时器，全部结束后汇总结果并报告冲突
+fn register_pending_hotkeys(hwnd: HWND) {
+    let mut statuses = HOTKEY_STATUSES.lock().unwrap();
+    let mut actions = HOTKEY_ACTIONS.lock().unwrap();
+    let mut next_attempt = None;
+
+    for status in statuses.iter_mut() {
+        let (Trigger::Hotkey(hotkey), HotkeyState::Retrying { attempt, .. }) =
+            (&status.trigger, &status.state)
+        else {
+            continue;
+        };
+        let attempt = *attempt;
+        match register_hotkey(hwnd, status.id, hotkey) {
+            Ok(()) => {
+                info!(
+                    "注册热键成功: {} -> {}",
+                    hotkey,
+                    status.action.description()
+                );
+                actions.insert(status.id, status.action.clone());
+                status.state = HotkeyState::Registered;
+            }
+            Err(err_code) => {
+                let reason = error_reason(err_code);
+                status.state = match retry_delay_ms(attempt) {
+                    Some(delay_ms) => {
+                        warn!(
+                            "注册热键失败: {} -> {} ({})，{} 毫秒后第 {} 次重试",
+                            hotkey,
+                            status.action.description(),
+                            reason,
+                            delay_ms,
+                            attempt + 1
+                        );
+                        next_attempt = Some(next_attempt.map_or(delay_ms, |t: u32| t.min(delay_ms)));
+                        HotkeyState::Retrying {
+                            attempt: attempt + 1,
+                            reason,
+                        }
+                    }
+                    None => {
+                        error!(
+                            "注册热键失败: {} -> {} ({})，已放弃重试",
+                            hotkey,
+                            status.action.description(),
+                            reason
+                        );
+                        HotkeyState::Failed { reason }
+                    }
+                };
             }
-        } else {
-            error!("switch_hotkey无效的热键格式: {}", hotkey_str);
         }
     }
+    drop(actions);
 
-    // 如果有热键注册失败，且还有重试次数，则延迟后重试
-    if success_count < total_count && max_retries > 0 {
-        info!("有热键注册失败，2秒后重试... (剩余 {} 次重试)", max_retries);
-        std::thread::sleep(Duration::from_secs(2));
+    if let Some(delay_ms) = next_attempt {
+        unsafe {
+            SetTimer(
+                Some(hwnd),
+                constants::TIMER_ID_HOTKEY_RETRY,
+                delay_ms,
+                None,
+            );
+        }
+        return;
+    }
 
-        // 先注销已注册的热键，然后重新注册
-        unregister_all_hotkeys(hwnd);
-        register_all_hotkeys_with_retry(hwnd, max_retries - 1);
-    } else if success_count == total_count {
+    // 重试结束，汇总结果
+    let registrable: Vec<&BindingStatus> = statuses
+        .iter()
+        .filter(|s| !matches!(s.state, HotkeyState::Hooked))
+        .collect();
+    let registered = registrable
+        .iter()
+        .filter(|s| s.state == HotkeyState::Registered)
+        .count();
+    if registered == registrable.len() {
         info!("所有的热键注册成功");
-    } else {
+    } else if registered == 0 {
         error!("所有热键注册失败");
+    } else {
+        warn!(
+            "部分热键注册失败: 成功 {} 个，失败 {} 个",
+            registered,
+            registrable.len() - registered
+        );
+    }
+
+    let conflicts = registrable
+        .iter()
+        .filter(|s| s.is_conflict())
+        .filter_map(|s| match s.trigger {
+            Trigger::Hotkey(hotkey) => Some((hotkey, s.action.clone())),
+            _ => None,
+        })
+        .collect();
+    drop(statuses);
+    report_conflicts(hwnd, conflicts);
+}
+
+// 各绑定当前的注册状态
+pub fn binding_statuses() -> Vec<BindingStatus> {
+    HOTKEY_STATUSES.lock().unwrap().clone()
+}
+
+// 为被占用的热键查找可用的替代组合，并通过托盘通知告知用户（包括配置中的重复绑定）
+fn report_conflicts(hwnd: HWND, conflicts: Vec<(Hotkey, Action)>) {
+    let config = config::CONFIG.read().unwrap();
+    let mut taken: Vec<Hotkey> = config
+        .bindings()
+        .keys()
+        .filter_map(|trigger| match trigger {
+            Trigger::Hotkey(hotkey) => Some(*hotkey),
+            _ => None,
+        })
+        .collect();
+    let mut lines = config.duplicate_bindings.clone();
+    drop(config);
+
+    let conflicts: Vec<HotkeyConflict> = conflicts
+        .into_iter()
+        .map(|(hotkey, action)| {
+            let alternatives = find_free_alternatives(hwnd, &hotkey, &taken);
+            // 避免为多个冲突推荐同一个组合
+            taken.extend(alternatives.iter().copied());
+            warn!(
+                "热键 {} ({}) 已被其他程序占用，可用的替代组合: {:?}",
+                hotkey,
+                action.description(),
+                alternatives.iter().map(Hotkey::to_string).collect::<Vec<_>>()
+            );
+            lines.push(format!(
+                "{} ({}) 已被其他程序占用",
+                hotkey,
+                action.description()
+            ));
+            HotkeyConflict {
+                hotkey,
+                action,
+                alternatives,
+            }
+        })
+        .collect();
+
+    let has_alternatives = conflicts.iter().any(|c| !c.alternatives.is_empty());
+    *HOTKEY_CONFLICTS.lock().unwrap() = conflicts;
+
+    if lines.is_empty() {
+        return;
+    }
+    if has_alternatives {
+        lines.push("可在托盘菜单「热键冲突」中选择替代热键".to_string());
+    }
+    show_balloon_tip(hwnd, "热键冲突", &lines.join("\n"));
+}
+
+// 依次尝试注册候选组合（成功后立即注销），返回前几个可用的组合
+fn find_free_alternatives(hwnd: HWND, hotkey: &Hotkey, taken: &[Hotkey]) -> Vec<Hotkey> {
+    rank_alternatives(hotkey, taken)
+        .into_iter()
+        .filter(|candidate| unsafe {
+            let free = RegisterHotKey(
+                Some(hwnd),
+                constants::HOT_KEY_PROBE_ID,
+                HOT_KEY_MODIFIERS(candidate.modifiers),
+                candidate.key,
+            )
+            .is_ok();
+            if free {
+                unregister_hotkey(hwnd, constants::HOT_KEY_PROBE_ID);
+            }
+            free
+        })
+        .take(MAX_ALTERNATIVES)
+        .collect()
+}
+
+// 最近一次注册时发现的冲突
+pub fn hotkey_conflicts() -> Vec<HotkeyConflict> {
+    HOTKEY_CONFLICTS.lock().unwrap().clone()
+}
+
+// 采用替代组合：写回配置并重新注册热键
+pub fn accept_alternative(hwnd: HWND, conflict_index: usize, alternative_index: usize) {
+    let Some((conflict, alternative)) = HOTKEY_CONFLICTS
+        .lock()
+        .unwrap()
+        .get(conflict_index)
+        .and_then(|c| Some((c.clone(), *c.alternatives.get(alternative_index)?)))
+    else {
+        return;
+    };
+
+    let mut config = config::CONFIG.write().unwrap();
+    let bindings = config.bindings_mut();
+    bindings.remove(&Trigger::Hotkey(conflict.hotkey));
+    bindings.insert(Trigger::Hotkey(alternative), conflict.action.clone());
+    if let Err(e) = config.save() {
+        error!("保存配置文件失败: {}", e);
+    }
+    drop(config);
+
+    info!(
+        "热键 {} ({}) 改为 {}",
+        conflict.hotkey,
+        conflict.action.description(),
+        alternative
+    );
+    register_all_hotkeys(hwnd);
+    show_balloon_tip(
+        hwnd,
+        "热键已更新",
+        &format!(
+            "{} 的热键已由 {} 改为 {}",
+            conflict.action.description(),
+            conflict.hotkey,
+            alternative
+        ),
+    );
+}
+
+// 热键已被其他程序注册
+const ERROR_HOTKEY_ALREADY_REGISTERED: u32 = 1409;
+const REASON_ALREADY_REGISTERED: &str = "热键已被注册";
+
+fn error_reason(err_code: u32) -> String {
+    match err_code {
+        ERROR_HOTKEY_ALREADY_REGISTERED => REASON_ALREADY_REGISTERED.to_string(),
+        1408 => "尝试注销一个未注册的热键或ID不合法".to_string(),
+        _ => format!("未知错误 (错误码 {})", err_code),
     }
 }
 
 // 注册和注销热键
-fn register_hotkey(hwnd: HWND, id: i32, modifiers: u32, key: u32) -> bool {
+fn register_hotkey(hwnd: HWND, id: i32, hotkey: &Hotkey) -> Result<(), u32> {
     unsafe {
-        if RegisterHotKey(Some(hwnd), id, HOT_KEY_MODIFIERS(modifiers), key).is_ok() {
-            true
+        if RegisterHotKey(
+            Some(hwnd),
+            id,
+            HOT_KEY_MODIFIERS(hotkey.modifiers),
+            hotkey.key,
+        )
+        .is_ok()
+        {
+            Ok(())
         } else {
-            let err_code = GetLastError().0;
-            let err_msg = match err_code {
-                1409 => "热键已被注册".to_string(),
-                1408 => "尝试注销一个未注册的热键或ID不合法".to_string(),
-                _ => format!("未知错误"),
-            };
-            error!("注册热键失败，错误码: {}，原因: {}", err_code, err_msg);
-            false
+            Err(GetLastError().0)
         }
     }
 }
@@ -89,8 +514,20 @@ fn unregister_hotkey(hwnd: HWND, id: i32) -> bool {
     unsafe { UnregisterHotKey(Some(hwnd), id).is_ok() }
 }
 
-// 注销所有热键
-fn unregister_all_hotkeys(hwnd: HWND) {
-    unregister_hotkey(hwnd, constants::HOT_KEY_TOGGLE_ID);
-    unregister_hotkey(hwnd, constants::HOT_KEY_SWITCH_MODE_ID);
+// 注销所有热键并停止重试
+pub fn unregister_all_hotkeys(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(Some(hwnd), constants::TIMER_ID_HOTKEY_RETRY);
+    }
+    let mut statuses = HOTKEY_STATUSES.lock().unwrap();
+    let mut actions = HOTKEY_ACTIONS.lock().unwrap();
+    for id in actions.keys() {
+        unregister_hotkey(hwnd, *id);
+    }
+    actions.clear();
+    for status in statuses.iter_mut() {
+        if status.state != HotkeyState::Hooked {
+            status.state = HotkeyState::Paused;
+        }
+    }
 }
@@ -0,0 +1,33 @@
+use crate::utils::parse_hotkey::{Hotkey, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN};
+
+// 为被占用的热键生成候选替代组合：保持按键不变，更换修饰键。
+// 排序规则：含 Win 的组合大多被系统占用，排在最后；其次保留原有修饰键优先（只增加修饰键最容易记住），
+// 再次改动越少越好，最后按修饰键数量从少到多
+pub fn rank_alternatives(hotkey: &Hotkey, taken: &[Hotkey]) -> Vec<Hotkey> {
+    const ALL: [u32; 4] = [MOD_CONTROL, MOD_ALT, MOD_SHIFT, MOD_WIN];
+
+    let mut candidates: Vec<Hotkey> = (1u32..16)
+        .map(|mask| {
+            ALL.iter()
+                .enumerate()
+                .filter(|&(i, _)| mask & (1 << i) != 0)
+                .fold(0, |modifiers, (_, &bit)| modifiers | bit)
+        })
+        .filter(|&modifiers| modifiers != hotkey.modifiers)
+        .map(|modifiers| Hotkey::new(modifiers, hotkey.key))
+        .filter(|candidate| !taken.iter().any(|t| t.overlaps(candidate)))
+        .collect();
+
+    candidates.sort_by_key(|candidate| {
+        let removed = (hotkey.modifiers & !candidate.modifiers).count_ones();
+        let changed = (hotkey.modifiers ^ candidate.modifiers).count_ones();
+        (
+            candidate.modifiers & MOD_WIN != 0,
+            removed,
+            changed,
+            candidate.modifiers.count_ones(),
+            candidate.modifiers,
+        )
+    });
+    candidates
+}
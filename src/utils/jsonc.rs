@@ -0,0 +1,551 @@
+use serde_json::Value;
+
+// JSONC：允许 // 与 /* */ 注释以及末尾多余逗号的 JSON。
+// 读取时把注释和多余的逗号替换为空格（保留换行），文本长度和位置都不变，再交给 serde_json 解析，
+// 错误的行列号与原文一致。修改时在替换后的文本中定位字段，再对原文的相同位置做替换，其余内容原样保留
+
+pub fn strip(content: &str) -> String {
+    let mut bytes = content.as_bytes().to_vec();
+    blank_comments(&mut bytes);
+    blank_trailing_commas(&mut bytes);
+    // 只把 ASCII 之外的注释字节逐个换成空格，结果仍是合法的 UTF-8
+    String::from_utf8(bytes).unwrap_or_else(|_| content.to_string())
+}
+
+fn blank_comments(bytes: &mut [u8]) {
+    let mut i = 0;
+    let mut in_string = false;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if in_string {
+            match c {
+                b'\\' => i += 1,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        match (c, bytes.get(i + 1)) {
+            (b'"', _) => {
+                in_string = true;
+                i += 1;
+            }
+            (b'/', Some(b'/')) => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    blank(bytes, i);
+                    i += 1;
+                }
+            }
+            (b'/', Some(b'*')) => {
+                let end = find(bytes, i + 2, b"*/").map_or(bytes.len(), |end| end + 2);
+                (i..end).for_each(|j| blank(bytes, j));
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+// 只去掉注释，保留末尾多余的逗号，用于判断原文中逗号的位置
+fn without_comments(content: &str) -> Vec<u8> {
+    let mut bytes = content.as_bytes().to_vec();
+    blank_comments(&mut bytes);
+    bytes
+}
+
+fn blank(bytes: &mut [u8], i: usize) {
+    if bytes[i] != b'\n' && bytes[i] != b'\r' {
+        bytes[i] = b' ';
+    }
+}
+
+fn find(bytes: &[u8], from: usize, pattern: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|index| from + index)
+}
+
+// 注释已替换为空格，逗号之后只隔空白就是 } 或 ] 时为多余的逗号
+fn blank_trailing_commas(bytes: &mut [u8]) {
+    let mut pending_comma = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                pending_comma = None;
+                i = skip_string(bytes, i);
+                continue;
+            }
+            b',' => pending_comma = Some(i),
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    bytes[comma] = b' ';
+                }
+            }
+            c if c.is_ascii_whitespace() => {}
+            _ => pending_comma = None,
+        }
+        i += 1;
+    }
+}
+
+// 从开头的引号起跳过字符串，返回结束引号之后的位置
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+// 对象中的一个成员在文本中的位置
+struct Member {
+    key: String,
+    key_start: usize,
+    value_start: usize,
+    value_end: usize,
+}
+
+// 对象的所有成员及其右花括号的位置
+struct Object {
+    members: Vec<Member>,
+    close: usize,
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+// 跳过一个值，返回其结束位置
+fn skip_value(bytes: &[u8], start: usize) -> Result<usize, String> {
+    match bytes.get(start) {
+        Some(b'"') => Ok(skip_string(bytes, start)),
+        Some(b'{' | b'[') => {
+            let mut depth = 0;
+            let mut i = start;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        i = skip_string(bytes, i);
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            Err("括号不匹配".to_string())
+        }
+        Some(_) => {
+            let mut i = start;
+            while i < bytes.len() && !matches!(bytes[i], b',' | b'}' | b']') {
+                if bytes[i].is_ascii_whitespace() {
+                    break;
+                }
+                i += 1;
+            }
+            Ok(i)
+        }
+        None => Err("文件意外结束".to_string()),
+    }
+}
+
+fn parse_object(bytes: &[u8], open: usize) -> Result<Object, String> {
+    if bytes.get(open) != Some(&b'{') {
+        return Err("不是对象".to_string());
+    }
+    let mut members = Vec::new();
+    let mut i = skip_whitespace(bytes, open + 1);
+    loop {
+        match bytes.get(i) {
+            Some(b'}') => return Ok(Object { members, close: i }),
+            Some(b'"') => {}
+            _ => return Err(format!("位置 {} 处应为字段名", i)),
+        }
+        let key_start = i;
+        let key_end = skip_string(bytes, i);
+        let key = serde_json::from_slice::<String>(&bytes[key_start..key_end])
+            .map_err(|e| e.to_string())?;
+        i = skip_whitespace(bytes, key_end);
+        if bytes.get(i) != Some(&b':') {
+            return Err(format!("字段 {} 之后应为冒号", key));
+        }
+        let value_start = skip_whitespace(bytes, i + 1);
+        let value_end = skip_value(bytes, value_start)?;
+        members.push(Member {
+            key,
+            key_start,
+            value_start,
+            value_end,
+        });
+        i = skip_whitespace(bytes, value_end);
+        if bytes.get(i) == Some(&b',') {
+            i = skip_whitespace(bytes, i + 1);
+        }
+    }
+}
+
+// 可修改的 JSONC 文档
+pub struct Document {
+    text: String,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    fn replace(&mut self, start: usize, end: usize, replacement: &str) {
+        self.text.replace_range(start..end, replacement);
+    }
+
+    // 所在行开头的缩进
+    fn indent_at(&self, position: usize) -> String {
+        let line_start = self.text[..position]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        self.text[line_start..]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect()
+    }
+
+    // 设置字段的值，中间缺少的对象会一并创建
+    pub fn set(&mut self, path: &[String], value: &Value) -> Result<(), String> {
+        let stripped = strip(&self.text);
+        let bytes = stripped.as_bytes();
+        let mut open = skip_whitespace(bytes, 0);
+        for (depth, key) in path.iter().enumerate() {
+            let object = parse_object(bytes, open)?;
+            let rest = &path[depth + 1..];
+            let Some(member) = object.members.iter().find(|member| &member.key == key) else {
+                return self.insert(&object, open, key, &nest(rest, value));
+            };
+            if !rest.is_empty() && bytes[member.value_start] == b'{' {
+                open = member.value_start;
+                continue;
+            }
+            let rendered = self.render(&nest(rest, value), member.key_start);
+            self.replace(member.value_start, member.value_end, &rendered);
+            return Ok(());
+        }
+        Err("路径为空".to_string())
+    }
+
+    // 删除字段（连同同一行的逗号和注释），字段不存在时不做修改
+    pub fn remove(&mut self, path: &[String]) -> Result<(), String> {
+        let stripped = strip(&self.text);
+        let bytes = stripped.as_bytes();
+        let mut open = skip_whitespace(bytes, 0);
+        for (depth, key) in path.iter().enumerate() {
+            let object = parse_object(bytes, open)?;
+            let Some(index) = object.members.iter().position(|member| &member.key == key) else {
+                return Ok(());
+            };
+            let member = &object.members[index];
+            if depth + 1 < path.len() {
+                open = member.value_start;
+                continue;
+            }
+
+            let commas = without_comments(&self.text);
+            let after = skip_whitespace(&commas, member.value_end);
+            let had_comma = commas.get(after) == Some(&b',');
+            let mut end = if had_comma {
+                after + 1
+            } else {
+                member.value_end
+            };
+            let line_start = self.text[..member.key_start]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+            let own_line = self.text[line_start..member.key_start].trim().is_empty();
+            let line_end = self.text[end..].find('\n').map(|i| end + i);
+            let start = match line_end {
+                // 独占一行的字段连同注释整行删除
+                Some(line_end)
+                    if own_line
+                        && stripped[end..line_end].trim().is_empty()
+                        && object.close > line_end =>
+                {
+                    end = line_end + 1;
+                    line_start
+                }
+                _ => member.key_start,
+            };
+            // 删除最后一个字段时，去掉前一个字段之后的逗号
+            if !had_comma && index > 0 {
+                let previous = &object.members[index - 1];
+                let comma = skip_whitespace(&commas, previous.value_end);
+                if commas.get(comma) == Some(&b',') {
+                    self.replace(start, end, "");
+                    self.replace(comma, comma + 1, "");
+                    return Ok(());
+                }
+            }
+            self.replace(start, end, "");
+            return Ok(());
+        }
+        Err("路径为空".to_string())
+    }
+
+    // 在对象末尾添加字段，沿用已有字段的缩进以及是否保留末尾逗号
+    fn insert(
+        &mut self,
+        object: &Object,
+        open: usize,
+        key: &str,
+        value: &Value,
+    ) -> Result<(), String> {
+        let key = serde_json::to_string(key).map_err(|e| e.to_string())?;
+        let Some(last) = object.members.last() else {
+            if !self.text.contains('\n') {
+                let rendered = self.render(value, open);
+                self.replace(open + 1, object.close, &format!("{}: {}", key, rendered));
+                return Ok(());
+            }
+            let newline = self.newline();
+            let indent = self.indent_at(open);
+            let inner = format!("{}  ", indent);
+            let rendered = indent_lines(&to_pretty(value), newline, &inner);
+            self.replace(
+                open + 1,
+                object.close,
+                &format!(
+                    "{}{}{}: {}{}{}",
+                    newline, inner, key, rendered, newline, indent
+                ),
+            );
+            return Ok(());
+        };
+
+        let indent = self.indent_at(last.key_start);
+        let rendered = self.render(value, last.key_start);
+        let commas = without_comments(&self.text);
+        let after = skip_whitespace(&commas, last.value_end);
+        let trailing_comma = commas.get(after) == Some(&b',');
+        let newline = self.newline();
+        let line_end = self.text[last.value_end..]
+            .find(newline)
+            .map(|i| last.value_end + i)
+            .filter(|line_end| *line_end < object.close);
+        match line_end {
+            // 字段各占一行：在最后一个字段所在行之后另起一行
+            Some(line_end) => {
+                let comma = if trailing_comma { "," } else { "" };
+                self.replace(
+                    line_end,
+                    line_end,
+                    &format!("{}{}{}: {}{}", newline, indent, key, rendered, comma),
+                );
+                if !trailing_comma {
+                    self.replace(last.value_end, last.value_end, ",");
+                }
+            }
+            // 对象写在同一行
+            None => {
+                let at = if trailing_comma {
+                    after + 1
+                } else {
+                    last.value_end
+                };
+                let comma = if trailing_comma { "," } else { "" };
+                let prefix = if trailing_comma { " " } else { ", " };
+                self.replace(at, at, &format!("{}{}: {}{}", prefix, key, rendered, comma));
+            }
+        }
+        Ok(())
+    }
+
+    // 多行的值沿用字段所在行的缩进；整个文件写在一行时值也写在一行
+    fn render(&self, value: &Value, key_start: usize) -> String {
+        if !self.text.contains('\n') {
+            return serde_json::to_string(value).unwrap_or_default();
+        }
+        indent_lines(
+            &to_pretty(value),
+            self.newline(),
+            &self.indent_at(key_start),
+        )
+    }
+
+    // 沿用原文的换行符
+    fn newline(&self) -> &'static str {
+        if self.text.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+}
+
+// 把剩余的路径包装为嵌套的对象
+fn nest(path: &[String], value: &Value) -> Value {
+    path.iter().rev().fold(value.clone(), |value, key| {
+        let mut object = serde_json::Map::new();
+        object.insert(key.clone(), value);
+        Value::Object(object)
+    })
+}
+
+fn to_pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+// 第一行之外的每一行加上缩进
+fn indent_lines(text: &str, newline: &str, indent: &str) -> String {
+    text.replace('\n', &format!("{}{}", newline, indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    fn set(text: &str, keys: &[&str], value: Value) -> String {
+        let mut document = Document::new(text);
+        document.set(&path(keys), &value).unwrap();
+        document.into_string()
+    }
+
+    fn remove(text: &str, keys: &[&str]) -> String {
+        let mut document = Document::new(text);
+        document.remove(&path(keys)).unwrap();
+        document.into_string()
+    }
+
+    const CONFIG: &str = r#"{
+  // 总开关
+  "mode": 1, // 行尾注释
+  "enabled": true,
+  /* 快捷键 */
+  "bindings": {
+    "Ctrl+Space": "ToggleMasterSwitch" // 切换
+  }
+}
+"#;
+
+    #[test]
+    fn strip_keeps_positions() {
+        let stripped = strip(CONFIG);
+        assert_eq!(stripped.len(), CONFIG.len());
+        assert_eq!(stripped.lines().count(), CONFIG.lines().count());
+        let value: Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value["mode"], json!(1));
+    }
+
+    #[test]
+    fn scalar_change_keeps_everything_else() {
+        assert_eq!(
+            set(CONFIG, &["mode"], json!(2)),
+            CONFIG.replace("\"mode\": 1,", "\"mode\": 2,")
+        );
+    }
+
+    #[test]
+    fn nested_insert_and_removal() {
+        let inserted = set(CONFIG, &["bindings", "Ctrl+K"], json!("CycleMode"));
+        assert_eq!(
+            inserted,
+            CONFIG.replace(
+                "\"ToggleMasterSwitch\" // 切换\n",
+                "\"ToggleMasterSwitch\", // 切换\n    \"Ctrl+K\": \"CycleMode\"\n"
+            )
+        );
+        assert_eq!(remove(&inserted, &["bindings", "Ctrl+K"]), CONFIG);
+
+        // 缺少的上层对象一并创建
+        let created = set(CONFIG, &["ime", "target"], json!("zh"));
+        assert!(created.starts_with(&CONFIG[..CONFIG.len() - 3]));
+        assert!(created.ends_with("},\n  \"ime\": {\n    \"target\": \"zh\"\n  }\n}\n"));
+        assert_eq!(remove(&created, &["ime"]), CONFIG);
+    }
+
+    #[test]
+    fn removal_takes_line_comment_with_it() {
+        assert_eq!(
+            remove(CONFIG, &["mode"]),
+            CONFIG.replace("  \"mode\": 1, // 行尾注释\n", "")
+        );
+        // 删除不存在的字段不做修改
+        assert_eq!(remove(CONFIG, &["missing", "key"]), CONFIG);
+    }
+
+    #[test]
+    fn strings_with_comment_like_content() {
+        let text = r#"{
+  "url": "http://example.com/a,b}", // 地址
+  "mode": 1
+}"#;
+        assert_eq!(
+            set(text, &["mode"], json!(2)),
+            text.replace("\"mode\": 1", "\"mode\": 2")
+        );
+        assert_eq!(
+            set(text, &["url"], json!("a // b, }")),
+            text.replace("http://example.com/a,b}", "a // b, }")
+        );
+        assert_eq!(
+            remove(text, &["mode"]),
+            "{\n  \"url\": \"http://example.com/a,b}\" // 地址\n}"
+        );
+    }
+
+    #[test]
+    fn trailing_commas_are_kept() {
+        let text = "{\n  \"a\": 1,\n  \"b\": [1, 2,],\n}";
+        assert_eq!(
+            set(text, &["c"], json!(3)),
+            "{\n  \"a\": 1,\n  \"b\": [1, 2,],\n  \"c\": 3,\n}"
+        );
+        assert_eq!(set(text, &["a"], json!(0)), text.replace("1,\n", "0,\n"));
+        assert_eq!(remove(text, &["a"]), "{\n  \"b\": [1, 2,],\n}");
+
+        let inline = r#"{"a": 1, "b": 2,}"#;
+        assert_eq!(
+            set(inline, &["c"], json!(3)),
+            r#"{"a": 1, "b": 2, "c": 3,}"#
+        );
+    }
+
+    #[test]
+    fn crlf_is_kept() {
+        let text = CONFIG.replace('\n', "\r\n");
+        let inserted = set(&text, &["bindings", "Ctrl+K"], json!("CycleMode"));
+        assert_eq!(
+            inserted,
+            text.replace(
+                "\"ToggleMasterSwitch\" // 切换\r\n",
+                "\"ToggleMasterSwitch\", // 切换\r\n    \"Ctrl+K\": \"CycleMode\"\r\n"
+            )
+        );
+        assert!(!inserted.replace("\r\n", "").contains('\n'));
+        assert_eq!(remove(&inserted, &["bindings", "Ctrl+K"]), text);
+    }
+}
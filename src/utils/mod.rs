@@ -1,3 +1,15 @@
 pub mod parse_hotkey;
 pub mod check_autostart_status;
-pub mod hot_key;
\ No newline at end of file
+pub mod config_file;
+pub mod config_format;
+pub mod config_location;
+pub mod config_migration;
+pub mod config_patch;
+pub mod config_watcher;
+pub mod hot_key;
+pub mod hotkey_alternatives;
+pub mod jsonc;
+#[cfg(test)]
+pub mod test_util;
+pub mod trigger;
+pub mod window_process;
\ No newline at end of file
@@ -1,83 +1,738 @@
-use log::info;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 
-// 热键解析功能
-pub fn parse_hotkey(hotkey_str: &str) -> Option<(u32, u32)> {
-    let parts: Vec<&str> = hotkey_str.split('+').collect();
-    info!("发现hot key : {:?}", parts);
+// 按键名称 -> 虚拟键码。同一键码的第一个名称为规范名称，之后的为别名；匹配时不区分大小写
+pub const KEY_TABLE: &[(&str, u32)] = &[
+    // 字母与数字
+    ("A", 0x41),
+    ("B", 0x42),
+    ("C", 0x43),
+    ("D", 0x44),
+    ("E", 0x45),
+    ("F", 0x46),
+    ("G", 0x47),
+    ("H", 0x48),
+    ("I", 0x49),
+    ("J", 0x4A),
+    ("K", 0x4B),
+    ("L", 0x4C),
+    ("M", 0x4D),
+    ("N", 0x4E),
+    ("O", 0x4F),
+    ("P", 0x50),
+    ("Q", 0x51),
+    ("R", 0x52),
+    ("S", 0x53),
+    ("T", 0x54),
+    ("U", 0x55),
+    ("V", 0x56),
+    ("W", 0x57),
+    ("X", 0x58),
+    ("Y", 0x59),
+    ("Z", 0x5A),
+    ("0", 0x30),
+    ("1", 0x31),
+    ("2", 0x32),
+    ("3", 0x33),
+    ("4", 0x34),
+    ("5", 0x35),
+    ("6", 0x36),
+    ("7", 0x37),
+    ("8", 0x38),
+    ("9", 0x39),
+    // 功能键
+    ("F1", 0x70),
+    ("F2", 0x71),
+    ("F3", 0x72),
+    ("F4", 0x73),
+    ("F5", 0x74),
+    ("F6", 0x75),
+    ("F7", 0x76),
+    ("F8", 0x77),
+    ("F9", 0x78),
+    ("F10", 0x79),
+    ("F11", 0x7A),
+    ("F12", 0x7B),
+    ("F13", 0x7C),
+    ("F14", 0x7D),
+    ("F15", 0x7E),
+    ("F16", 0x7F),
+    ("F17", 0x80),
+    ("F18", 0x81),
+    ("F19", 0x82),
+    ("F20", 0x83),
+    ("F21", 0x84),
+    ("F22", 0x85),
+    ("F23", 0x86),
+    ("F24", 0x87),
+    // 编辑与导航
+    ("Backspace", 0x08),
+    ("Tab", 0x09),
+    ("Clear", 0x0C),
+    ("Enter", 0x0D),
+    ("Pause", 0x13),
+    ("CapsLock", 0x14),
+    ("Esc", 0x1B),
+    ("Space", 0x20),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("End", 0x23),
+    ("Home", 0x24),
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("Select", 0x29),
+    ("Print", 0x2A),
+    ("Execute", 0x2B),
+    ("PrintScreen", 0x2C),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Help", 0x2F),
+    ("Apps", 0x5D),
+    ("Sleep", 0x5F),
+    ("NumLock", 0x90),
+    ("ScrollLock", 0x91),
+    // 输入法相关
+    ("Kana", 0x15),
+    ("Junja", 0x17),
+    ("Final", 0x18),
+    ("Kanji", 0x19),
+    ("Convert", 0x1C),
+    ("NonConvert", 0x1D),
+    ("Accept", 0x1E),
+    ("ModeChange", 0x1F),
+    ("ProcessKey", 0xE5),
+    // 小键盘
+    ("Numpad0", 0x60),
+    ("Numpad1", 0x61),
+    ("Numpad2", 0x62),
+    ("Numpad3", 0x63),
+    ("Numpad4", 0x64),
+    ("Numpad5", 0x65),
+    ("Numpad6", 0x66),
+    ("Numpad7", 0x67),
+    ("Numpad8", 0x68),
+    ("Numpad9", 0x69),
+    ("NumpadMultiply", 0x6A),
+    ("NumpadAdd", 0x6B),
+    ("Separator", 0x6C),
+    ("NumpadSubtract", 0x6D),
+    ("NumpadDecimal", 0x6E),
+    ("NumpadDivide", 0x6F),
+    // 浏览器与多媒体
+    ("BrowserBack", 0xA6),
+    ("BrowserForward", 0xA7),
+    ("BrowserRefresh", 0xA8),
+    ("BrowserStop", 0xA9),
+    ("BrowserSearch", 0xAA),
+    ("BrowserFavorites", 0xAB),
+    ("BrowserHome", 0xAC),
+    ("VolumeMute", 0xAD),
+    ("VolumeDown", 0xAE),
+    ("VolumeUp", 0xAF),
+    ("MediaNext", 0xB0),
+    ("MediaPrev", 0xB1),
+    ("MediaStop", 0xB2),
+    ("MediaPlayPause", 0xB3),
+    ("LaunchMail", 0xB4),
+    ("LaunchMediaSelect", 0xB5),
+    ("LaunchApp1", 0xB6),
+    ("LaunchApp2", 0xB7),
+    // 标点符号 (美式键盘布局)
+    (";", 0xBA),
+    ("=", 0xBB),
+    ("Comma", 0xBC),
+    ("-", 0xBD),
+    (".", 0xBE),
+    ("/", 0xBF),
+    ("`", 0xC0),
+    ("[", 0xDB),
+    ("\\", 0xDC),
+    ("]", 0xDD),
+    ("'", 0xDE),
+    ("OEM_102", 0xE2),
+    // 别名
+    ("Back", 0x08),
+    ("BS", 0x08),
+    ("Return", 0x0D),
+    ("Break", 0x13),
+    ("Caps", 0x14),
+    ("Capital", 0x14),
+    ("Escape", 0x1B),
+    ("Spacebar", 0x20),
+    ("PgUp", 0x21),
+    ("Prior", 0x21),
+    ("PgDn", 0x22),
+    ("Next", 0x22),
+    ("LeftArrow", 0x25),
+    ("UpArrow", 0x26),
+    ("RightArrow", 0x27),
+    ("DownArrow", 0x28),
+    ("PrtSc", 0x2C),
+    ("PrtScn", 0x2C),
+    ("Snapshot", 0x2C),
+    ("Ins", 0x2D),
+    ("Del", 0x2E),
+    ("Menu", 0x5D),
+    ("ContextMenu", 0x5D),
+    ("Scroll", 0x91),
+    ("Hangul", 0x15),
+    ("Hanja", 0x19),
+    ("Num0", 0x60),
+    ("Num1", 0x61),
+    ("Num2", 0x62),
+    ("Num3", 0x63),
+    ("Num4", 0x64),
+    ("Num5", 0x65),
+    ("Num6", 0x66),
+    ("Num7", 0x67),
+    ("Num8", 0x68),
+    ("Num9", 0x69),
+    ("Multiply", 0x6A),
+    ("Add", 0x6B),
+    ("Subtract", 0x6D),
+    ("Decimal", 0x6E),
+    ("Divide", 0x6F),
+    ("Mute", 0xAD),
+    ("NextTrack", 0xB0),
+    ("PrevTrack", 0xB1),
+    ("MediaPrevious", 0xB1),
+    ("PlayPause", 0xB3),
+    ("Mail", 0xB4),
+    ("Semicolon", 0xBA),
+    ("OEM_1", 0xBA),
+    ("Equals", 0xBB),
+    ("Plus", 0xBB),
+    ("+", 0xBB),
+    ("OEM_PLUS", 0xBB),
+    (",", 0xBC),
+    ("OEM_COMMA", 0xBC),
+    ("Minus", 0xBD),
+    ("OEM_MINUS", 0xBD),
+    ("Period", 0xBE),
+    ("Dot", 0xBE),
+    ("OEM_PERIOD", 0xBE),
+    ("Slash", 0xBF),
+    ("OEM_2", 0xBF),
+    ("Backtick", 0xC0),
+    ("Grave", 0xC0),
+    ("Tilde", 0xC0),
+    ("OEM_3", 0xC0),
+    ("LBracket", 0xDB),
+    ("OEM_4", 0xDB),
+    ("Backslash", 0xDC),
+    ("OEM_5", 0xDC),
+    ("RBracket", 0xDD),
+    ("OEM_6", 0xDD),
+    ("Quote", 0xDE),
+    ("Apostrophe", 0xDE),
+    ("OEM_7", 0xDE),
+    ("IntlBackslash", 0xE2),
+];
 
-    // if parts.len() < 2 {
-    //     return None;
-    // }
+pub const MOD_ALT: u32 = 0x0001;
+pub const MOD_CONTROL: u32 = 0x0002;
+pub const MOD_SHIFT: u32 = 0x0004;
+pub const MOD_WIN: u32 = 0x0008;
 
-    let mut modifiers = 0u32;
-    let mut key_code = 0u32;
-
-    for (i, part) in parts.iter().enumerate() {
-        let part = part.trim();
-        if i == parts.len() - 1 {
-            // 最后一个是按键
-            key_code = match part.to_uppercase().as_str() {
-                "A" => 65,
-                "B" => 66,
-                "C" => 67,
-                "D" => 68,
-                "E" => 69,
-                "F" => 70,
-                "G" => 71,
-                "H" => 72,
-                "I" => 73,
-                "J" => 74,
-                "K" => 75,
-                "L" => 76,
-                "M" => 77,
-                "N" => 78,
-                "O" => 79,
-                "P" => 80,
-                "Q" => 81,
-                "R" => 82,
-                "S" => 83,
-                "T" => 84,
-                "U" => 85,
-                "V" => 86,
-                "W" => 87,
-                "X" => 88,
-                "Y" => 89,
-                "Z" => 90,
-                "F1" => 0x70,
-                "F2" => 0x71,
-                "F3" => 0x72,
-                "F4" => 0x73,
-                "F5" => 0x74,
-                "F6" => 0x75,
-                "F7" => 0x76,
-                "F8" => 0x77,
-                "F9" => 0x78,
-                "F10" => 0x79,
-                "F11" => 0x7A,
-                "F12" => 0x7B,
-                "SPACE" => 0x20,
-                "ENTER" => 0x0D,
-                "ESC" => 0x1B,
-                "TAB" => 0x09,
-                "BACKSPACE" => 0x08,
-                "CAPSLOCK" => 0x14,
-                _ => return None,
-            };
+// 区分左右的修饰键，RegisterHotKey 不支持，带有这些位的热键通过键盘钩子处理
+pub const MOD_LALT: u32 = 0x0001_0000;
+pub const MOD_RALT: u32 = 0x0002_0000;
+pub const MOD_LCONTROL: u32 = 0x0004_0000;
+pub const MOD_RCONTROL: u32 = 0x0008_0000;
+pub const MOD_LSHIFT: u32 = 0x0010_0000;
+pub const MOD_RSHIFT: u32 = 0x0020_0000;
+pub const MOD_LWIN: u32 = 0x0040_0000;
+pub const MOD_RWIN: u32 = 0x0080_0000;
+
+// 每种修饰键的 (不区分左右, 左侧, 右侧) 位
+const MODIFIER_SIDES: [(u32, u32, u32); 4] = [
+    (MOD_ALT, MOD_LALT, MOD_RALT),
+    (MOD_CONTROL, MOD_LCONTROL, MOD_RCONTROL),
+    (MOD_SHIFT, MOD_LSHIFT, MOD_RSHIFT),
+    (MOD_WIN, MOD_LWIN, MOD_RWIN),
+];
+
+// 修饰键名称，按规范显示顺序排列；同一修饰键的第一个名称为规范名称
+const MODIFIER_TABLE: &[(&str, u32)] = &[
+    ("Ctrl", MOD_CONTROL),
+    ("Control", MOD_CONTROL),
+    ("LCtrl", MOD_LCONTROL),
+    ("RCtrl", MOD_RCONTROL),
+    ("Alt", MOD_ALT),
+    ("LAlt", MOD_LALT),
+    ("RAlt", MOD_RALT),
+    ("Shift", MOD_SHIFT),
+    ("LShift", MOD_LSHIFT),
+    ("RShift", MOD_RSHIFT),
+    ("Win", MOD_WIN),
+    ("Windows", MOD_WIN),
+    ("LWin", MOD_LWIN),
+    ("RWin", MOD_RWIN),
+];
+
+// 修饰键本身的虚拟键码，用于单独按下修饰键的触发方式（如 "Tap(LShift)"）及模拟按键
+pub const MODIFIER_KEY_TABLE: &[(&str, u32)] = &[
+    ("Shift", 0x10),
+    ("Ctrl", 0x11),
+    ("Control", 0x11),
+    ("Alt", 0x12),
+    ("LShift", 0xA0),
+    ("RShift", 0xA1),
+    ("LCtrl", 0xA2),
+    ("RCtrl", 0xA3),
+    ("LAlt", 0xA4),
+    ("RAlt", 0xA5),
+    ("LWin", 0x5B),
+    ("Win", 0x5B),
+    ("Windows", 0x5B),
+    ("RWin", 0x5C),
+];
+
+// 热键：修饰键 (RegisterHotKey 的 MOD_* 位，以及区分左右的 MOD_L*/MOD_R* 位) + 虚拟键码
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Hotkey {
+    pub modifiers: u32,
+    pub key: u32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum HotkeyParseError {
+    Empty,
+    InvalidSyntax {
+        column: usize,
+        message: &'static str,
+    },
+    MissingKey {
+        column: usize,
+    },
+    UnknownModifier {
+        token: String,
+        column: usize,
+        suggestion: Option<&'static str>,
+    },
+    UnknownKey {
+        token: String,
+        column: usize,
+        suggestion: Option<&'static str>,
+    },
+}
+
+impl fmt::Display for HotkeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, token, column, suggestion) = match self {
+            HotkeyParseError::Empty => return write!(f, "热键为空"),
+            HotkeyParseError::InvalidSyntax { column, message } => {
+                return write!(f, "第 {} 列: {}", column, message)
+            }
+            HotkeyParseError::MissingKey { column } => {
+                return write!(f, "第 {} 列缺少按键", column)
+            }
+            HotkeyParseError::UnknownModifier {
+                token,
+                column,
+                suggestion,
+            } => ("修饰键", token, column, suggestion),
+            HotkeyParseError::UnknownKey {
+                token,
+                column,
+                suggestion,
+            } => ("按键", token, column, suggestion),
+        };
+        write!(f, "第 {} 列的{} \"{}\" 无法识别", column, kind, token)?;
+        if let Some(suggestion) = suggestion {
+            write!(f, "，是否为 \"{}\"？", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HotkeyParseError {}
+
+impl Hotkey {
+    pub const fn new(modifiers: u32, key: u32) -> Self {
+        Self { modifiers, key }
+    }
+
+    // 是否使用了区分左右的修饰键（无法通过 RegisterHotKey 注册）
+    pub fn is_side_specific(&self) -> bool {
+        MODIFIER_SIDES
+            .iter()
+            .any(|&(_, left, right)| self.modifiers & (left | right) != 0)
+    }
+
+    // 按下 key 时是否触发该热键。pressed 为当前按下的修饰键，只含 MOD_L*/MOD_R* 位
+    pub fn matches(&self, pressed: u32, key: u32) -> bool {
+        self.key == key && modifiers_match(self.modifiers, pressed)
+    }
+
+    // 两个热键是否可能被同一次按键同时触发
+    pub fn overlaps(&self, other: &Hotkey) -> bool {
+        self.key == other.key
+            && MODIFIER_SIDES.iter().all(|&(generic, left, right)| {
+                let (a, b) = (self.modifiers, other.modifiers);
+                let (a_sides, b_sides) = (a & (left | right), b & (left | right));
+                match (a & generic != 0, b & generic != 0) {
+                    (true, true) => true,
+                    (true, false) => b_sides != 0 && a_sides & b_sides == a_sides,
+                    (false, true) => a_sides != 0 && a_sides & b_sides == b_sides,
+                    (false, false) => a_sides == b_sides,
+                }
+            })
+    }
+}
+
+// 按下的修饰键是否满足要求。pressed 只含 MOD_L*/MOD_R* 位。
+// 不区分左右的修饰键要求任意一侧按下，区分左右的要求对应一侧按下，未指定的修饰键不能按下
+pub fn modifiers_match(required: u32, pressed: u32) -> bool {
+    MODIFIER_SIDES.iter().all(|&(generic, left, right)| {
+        let held = pressed & (left | right);
+        let sides = required & (left | right);
+        if required & generic != 0 {
+            held != 0 && held & sides == sides
         } else {
-            // 修饰键
-            match part.to_uppercase().as_str() {
-                "CTRL" | "CONTROL" => modifiers |= 0x0002, // MOD_CONTROL
-                "ALT" => modifiers |= 0x0001,              // MOD_ALT
-                "SHIFT" => modifiers |= 0x0004,            // MOD_SHIFT
-                "WIN" | "WINDOWS" => modifiers |= 0x0008,  // MOD_WIN
-                _ => return None,
+            held == sides
+        }
+    })
+}
+
+// 拆分 "修饰键+...+按键"，解析修饰键，返回修饰键、按键记号及其列号
+pub fn split_modifiers(s: &str) -> Result<(u32, &str, usize), HotkeyParseError> {
+    if s.trim().is_empty() {
+        return Err(HotkeyParseError::Empty);
+    }
+
+    // 记号及其列号（去掉前导空白后，按字符计数，从 1 开始）
+    let mut tokens = Vec::new();
+    let mut column = 1;
+    for raw in s.split('+') {
+        let leading = raw.chars().count() - raw.trim_start().chars().count();
+        tokens.push((raw.trim(), column + leading));
+        column += raw.chars().count() + 1;
+    }
+
+    // 末尾的 "++" 中第二个 "+" 是按键本身，如 "Ctrl++"
+    if let [.., (second_last, column), (last, _)] = tokens[..] {
+        if second_last.is_empty() && last.is_empty() {
+            tokens.pop();
+            *tokens.last_mut().unwrap() = ("+", column);
+        }
+    }
+
+    let (&(key_token, key_column), modifier_tokens) = tokens.split_last().unwrap();
+
+    let mut modifiers = 0u32;
+    for &(token, column) in modifier_tokens {
+        if token.is_empty() {
+            return Err(HotkeyParseError::InvalidSyntax {
+                column,
+                message: "多余的 \"+\"，此处应为修饰键",
+            });
+        }
+        modifiers |= MODIFIER_TABLE
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(token))
+            .map(|&(_, bit)| bit)
+            .ok_or_else(|| HotkeyParseError::UnknownModifier {
+                token: token.to_string(),
+                column,
+                suggestion: closest_name(token, MODIFIER_TABLE),
+            })?;
+    }
+
+    if key_token.is_empty() {
+        return Err(HotkeyParseError::MissingKey { column: key_column });
+    }
+    Ok((modifiers, key_token, key_column))
+}
+
+// 修饰键的规范写法，如 "Ctrl+Shift+"
+pub fn modifiers_display(modifiers: u32) -> String {
+    let mut text = String::new();
+    let mut written = 0u32;
+    for &(name, bit) in MODIFIER_TABLE {
+        if modifiers & bit != 0 && written & bit == 0 {
+            written |= bit;
+            text.push_str(name);
+            text.push('+');
+        }
+    }
+    text
+}
+
+impl FromStr for Hotkey {
+    type Err = HotkeyParseError;
+
+    // 格式为 "修饰键+...+按键"，如 "Ctrl+Shift+F"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key_token, key_column) = split_modifiers(s)?;
+        let key = parse_key_name(key_token).ok_or_else(|| HotkeyParseError::UnknownKey {
+            token: key_token.to_string(),
+            column: key_column,
+            suggestion: closest_name(key_token, KEY_TABLE),
+        })?;
+
+        Ok(Hotkey { modifiers, key })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    // 规范格式，如 "Ctrl+Shift+F"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", modifiers_display(self.modifiers))?;
+        match key_name(self.key) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "VK_0x{:02X}", self.key),
+        }
+    }
+}
+
+impl Serialize for Hotkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hotkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e| de::Error::custom(format!("无效的热键 \"{}\": {}", s, e)))
+    }
+}
+
+// 在名称表中查找与输入最接近的名称（编辑距离不超过 2，且小于输入长度）
+pub fn closest_name<T>(token: &str, table: &[(&'static str, T)]) -> Option<&'static str> {
+    let token = token.to_lowercase();
+    table
+        .iter()
+        .map(|&(name, _)| (edit_distance(&token, &name.to_lowercase()), name))
+        .filter(|&(distance, _)| distance <= 2 && distance < token.chars().count())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, name)| name)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// 按键名称解析为虚拟键码，支持 "VK_0x41" 形式直接指定键码
+pub fn parse_key_name(name: &str) -> Option<u32> {
+    let name = name.trim();
+    if let Some(hex) = name
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("VK_0x"))
+        .map(|_| &name[5..])
+    {
+        return u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|code| (0x01..=0xFE).contains(code));
+    }
+
+    KEY_TABLE
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(_, code)| code)
+}
+
+// 虚拟键码对应的规范名称
+pub fn key_name(code: u32) -> Option<&'static str> {
+    KEY_TABLE
+        .iter()
+        .find(|&&(_, key_code)| key_code == code)
+        .map(|&(name, _)| name)
+}
+
+// 按键名称（含单独的修饰键）解析为虚拟键码
+pub fn parse_any_key(name: &str) -> Option<u32> {
+    parse_key_name(name).or_else(|| {
+        MODIFIER_KEY_TABLE
+            .iter()
+            .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name.trim()))
+            .map(|&(_, code)| code)
+    })
+}
+
+// 虚拟键码（含单独的修饰键）对应的规范名称
+pub fn any_key_name(code: u32) -> Option<&'static str> {
+    key_name(code).or_else(|| {
+        MODIFIER_KEY_TABLE
+            .iter()
+            .find(|&&(_, key_code)| key_code == code)
+            .map(|&(name, _)| name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Hotkey {
+        s.parse()
+            .unwrap_or_else(|e| panic!("\"{}\" 解析失败: {}", s, e))
+    }
+
+    #[test]
+    fn every_key_name_round_trips() {
+        for &(name, code) in KEY_TABLE {
+            for prefix in ["", "Ctrl+", "LAlt+"] {
+                let hotkey = parse(&format!("{}{}", prefix, name));
+                assert_eq!(hotkey.key, code, "{}", name);
+                let canonical = hotkey.to_string();
+                assert_eq!(canonical, format!("{}{}", prefix, key_name(code).unwrap()));
+                assert_eq!(parse(&canonical), hotkey);
             }
         }
     }
-    info!("解析热键: modifiers = {}, key_code = {}", modifiers, key_code);  
-    if key_code == 0 {
-        None
-    } else {
-        Some((modifiers, key_code))
+
+    #[test]
+    fn key_names_are_case_insensitive() {
+        for &(name, code) in KEY_TABLE {
+            assert_eq!(parse_key_name(&name.to_lowercase()), Some(code), "{}", name);
+            assert_eq!(parse_key_name(&name.to_uppercase()), Some(code), "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_modifier_name_round_trips() {
+        for &(name, bit) in MODIFIER_TABLE {
+            let hotkey = parse(&format!("{}+F5", name));
+            assert_eq!(hotkey, Hotkey::new(bit, 0x74), "{}", name);
+            assert_eq!(parse(&hotkey.to_string()), hotkey);
+        }
+        for &(name, code) in MODIFIER_KEY_TABLE {
+            assert_eq!(parse_any_key(name), Some(code), "{}", name);
+            assert_eq!(parse_any_key(any_key_name(code).unwrap()), Some(code));
+        }
+    }
+
+    #[test]
+    fn aliases_display_canonically() {
+        let cases = [
+            ("control+shift+f", "Ctrl+Shift+F"),
+            ("Shift+Ctrl+A", "Ctrl+Shift+A"),
+            ("Windows+e", "Win+E"),
+            ("Plus", "="),
+            ("Ctrl+Escape", "Ctrl+Esc"),
+            ("alt+PgDn", "Alt+PageDown"),
+            ("Ctrl+OEM_3", "Ctrl+`"),
+            ("Ctrl+,", "Ctrl+Comma"),
+            ("RShift+LCtrl+Num5", "LCtrl+RShift+Numpad5"),
+            ("Ctrl + Alt + Q", "Ctrl+Alt+Q"),
+        ];
+        for (input, canonical) in cases {
+            assert_eq!(parse(input).to_string(), canonical, "{}", input);
+        }
+    }
+
+    #[test]
+    fn vk_escape() {
+        for code in 0x01..=0xFE {
+            let hotkey = parse(&format!("Ctrl+VK_0x{:02X}", code));
+            assert_eq!(hotkey.key, code);
+            assert_eq!(parse(&hotkey.to_string()), hotkey);
+        }
+        assert_eq!(parse("vk_0x41").to_string(), "A");
+        assert_eq!(parse("VK_0x07").to_string(), "VK_0x07");
+        for invalid in ["VK_0x00", "VK_0xFF", "VK_0x100", "VK_0x", "VK_0xZZ"] {
+            assert!(invalid.parse::<Hotkey>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn trailing_plus_is_the_plus_key() {
+        assert_eq!(parse("Ctrl++"), Hotkey::new(MOD_CONTROL, 0xBB));
+        assert_eq!(parse("Ctrl + +"), Hotkey::new(MOD_CONTROL, 0xBB));
+        assert_eq!(
+            parse("LAlt+Shift++"),
+            Hotkey::new(MOD_LALT | MOD_SHIFT, 0xBB)
+        );
+        assert_eq!(parse("+"), Hotkey::new(0, 0xBB));
+        assert_eq!(parse("Ctrl++").to_string(), "Ctrl+=");
+    }
+
+    #[test]
+    fn errors_report_column_and_suggestion() {
+        let error = |s: &str| s.parse::<Hotkey>().unwrap_err();
+
+        assert_eq!(error(""), HotkeyParseError::Empty);
+        assert_eq!(error("   "), HotkeyParseError::Empty);
+        assert_eq!(
+            error("Ctl+M"),
+            HotkeyParseError::UnknownModifier {
+                token: "Ctl".to_string(),
+                column: 1,
+                suggestion: Some("Ctrl"),
+            }
+        );
+        assert_eq!(
+            error("Ctl+M").to_string(),
+            "第 1 列的修饰键 \"Ctl\" 无法识别，是否为 \"Ctrl\"？"
+        );
+        assert_eq!(
+            error("Ctrl + Shfit+A"),
+            HotkeyParseError::UnknownModifier {
+                token: "Shfit".to_string(),
+                column: 8,
+                suggestion: Some("Shift"),
+            }
+        );
+        assert_eq!(
+            error("Ctrl+Escap"),
+            HotkeyParseError::UnknownKey {
+                token: "Escap".to_string(),
+                column: 6,
+                suggestion: Some("Escape"),
+            }
+        );
+        assert_eq!(
+            error("Ctrl+Qwerty"),
+            HotkeyParseError::UnknownKey {
+                token: "Qwerty".to_string(),
+                column: 6,
+                suggestion: None,
+            }
+        );
+        // 列号按字符计数
+        assert_eq!(
+            error("Ctrl+ 中文"),
+            HotkeyParseError::UnknownKey {
+                token: "中文".to_string(),
+                column: 7,
+                suggestion: None,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_parts_are_reported() {
+        let error = |s: &str| s.parse::<Hotkey>().unwrap_err();
+
+        assert_eq!(error("Ctrl+"), HotkeyParseError::MissingKey { column: 6 });
+        assert_eq!(error("Ctrl+").to_string(), "第 6 列缺少按键");
+        assert_eq!(
+            error("Ctrl+Alt+ "),
+            HotkeyParseError::MissingKey { column: 11 }
+        );
+        assert_eq!(
+            error("Ctrl++A").to_string(),
+            "第 6 列: 多余的 \"+\"，此处应为修饰键"
+        );
+        assert!(matches!(
+            error("+A"),
+            HotkeyParseError::InvalidSyntax { column: 1, .. }
+        ));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,470 @@
+use crate::mouse::{mouse_button_name, parse_mouse_button, MouseButton, MOUSE_BUTTON_TABLE};
+use crate::utils::parse_hotkey::{
+    any_key_name, closest_name, modifiers_display, parse_any_key, parse_key_name, split_modifiers,
+    Hotkey, HotkeyParseError, KEY_TABLE, MODIFIER_KEY_TABLE,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// 绑定的触发方式：
+// - "Ctrl+Shift+F"：普通热键，通过 RegisterHotKey 注册；"RAlt+Space" 等区分左右修饰键的热键通过键盘钩子处理
+// - "RCtrl"：单独的修饰键，等同于 "Tap(RCtrl)"
+// - "Tap(LShift)"：单独轻按某个键（可以是修饰键），按住时保持原有功能
+// - "Tap(CapsLock, Hold=LCtrl)"：轻按触发动作，按住时当作另一个键使用
+// - "DoubleTap(Ctrl)"：快速连按两次某个键
+// - "Ctrl+K, E"：两段按键序列，先按前缀 Ctrl+K，再在超时时间内按 E
+// - "XButton1"、"Ctrl+MButton"、"WheelLeft"：鼠标按键及滚轮倾斜，通过低级鼠标钩子实现
+// 其余触发方式均通过低级键盘钩子实现
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Trigger {
+    Hotkey(Hotkey),
+    Tap { key: u32, hold: Option<u32> },
+    DoubleTap { key: u32 },
+    Chord { prefix: Hotkey, key: Hotkey },
+    Mouse { modifiers: u32, button: MouseButton },
+}
+
+impl FromStr for Trigger {
+    type Err = HotkeyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(open) = s.find('(') else {
+            return parse_strokes(s);
+        };
+
+        let name = s[..open].trim();
+        let close = s.trim_end().len() - 1;
+        if !s.trim_end().ends_with(')') || close < open {
+            return Err(HotkeyParseError::InvalidSyntax {
+                column: column_of(s, s.trim_end().len()),
+                message: "缺少右括号 \")\"",
+            });
+        }
+        let args = split_args(s, open + 1, close);
+
+        match name.to_ascii_lowercase().as_str() {
+            "tap" => parse_tap(&args, column_of(s, close)),
+            "doubletap" => parse_double_tap(&args, column_of(s, close)),
+            _ => Err(HotkeyParseError::InvalidSyntax {
+                column: column_of(s, s.len() - s.trim_start().len()),
+                message: "未知的触发方式，支持: Tap, DoubleTap",
+            }),
+        }
+    }
+}
+
+// 解析普通热键或按键序列，按键之间以逗号分隔。
+// 紧跟 "+" 或位于段首的逗号是按键本身（如 "Ctrl+,"），不是分隔符
+fn parse_strokes(s: &str) -> Result<Trigger, HotkeyParseError> {
+    let mut separators = Vec::new();
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let before = s[start..i].trim();
+        if c == ',' && !before.is_empty() && !before.ends_with('+') {
+            separators.push(i);
+            start = i + 1;
+        }
+    }
+
+    match separators[..] {
+        [] => parse_single_stroke(s),
+        [separator] => {
+            let prefix = s[..separator].parse()?;
+            let offset = column_of(s, separator + 1) - 1;
+            let key = match s[separator + 1..].parse() {
+                Err(HotkeyParseError::Empty) => Err(HotkeyParseError::MissingKey {
+                    column: column_of(s, s.len()),
+                }),
+                result => result.map_err(|e| shift_column(e, offset)),
+            }?;
+            Ok(Trigger::Chord { prefix, key })
+        }
+        [_, extra, ..] => Err(HotkeyParseError::InvalidSyntax {
+            column: column_of(s, extra),
+            message: "按键序列最多支持两段，如 \"Ctrl+K, E\"",
+        }),
+    }
+}
+
+// 解析单段按键：普通热键、鼠标按键（可带修饰键）或单独的修饰键
+fn parse_single_stroke(s: &str) -> Result<Trigger, HotkeyParseError> {
+    if let Ok((modifiers, token, _)) = split_modifiers(s) {
+        if let Some(button) = parse_mouse_button(token) {
+            return Ok(Trigger::Mouse { modifiers, button });
+        }
+    }
+    // 单独的修饰键（如 "RCtrl"）无法作为普通热键，视为轻按
+    if parse_key_name(s).is_none() {
+        if let Some(key) = parse_any_key(s) {
+            return Ok(Trigger::Tap { key, hold: None });
+        }
+    }
+    s.parse().map(Trigger::Hotkey).map_err(|e| match e {
+        // 按键名称可能是拼错的鼠标按键
+        HotkeyParseError::UnknownKey {
+            token,
+            column,
+            suggestion: None,
+        } => HotkeyParseError::UnknownKey {
+            suggestion: closest_name(&token, MOUSE_BUTTON_TABLE),
+            token,
+            column,
+        },
+        e => e,
+    })
+}
+
+// 子串解析错误的列号换算为整个字符串中的列号
+fn shift_column(error: HotkeyParseError, offset: usize) -> HotkeyParseError {
+    match error {
+        HotkeyParseError::InvalidSyntax { column, message } => HotkeyParseError::InvalidSyntax {
+            column: column + offset,
+            message,
+        },
+        HotkeyParseError::MissingKey { column } => HotkeyParseError::MissingKey {
+            column: column + offset,
+        },
+        HotkeyParseError::UnknownModifier {
+            token,
+            column,
+            suggestion,
+        } => HotkeyParseError::UnknownModifier {
+            token,
+            column: column + offset,
+            suggestion,
+        },
+        HotkeyParseError::UnknownKey {
+            token,
+            column,
+            suggestion,
+        } => HotkeyParseError::UnknownKey {
+            token,
+            column: column + offset,
+            suggestion,
+        },
+        HotkeyParseError::Empty => HotkeyParseError::Empty,
+    }
+}
+
+// 检查按键序列的前缀是否有歧义：前缀不能与普通热键重叠（如 "Ctrl+K" 与 "LCtrl+K"），
+// 否则按下前缀时无法判断是立即触发还是等待第二段按键
+pub fn check_chord_prefixes<'a>(
+    triggers: impl IntoIterator<Item = &'a Trigger>,
+) -> Result<(), String> {
+    let triggers: Vec<&Trigger> = triggers.into_iter().collect();
+    for trigger in &triggers {
+        let Trigger::Chord { prefix, .. } = trigger else {
+            continue;
+        };
+        for other in &triggers {
+            if let Trigger::Hotkey(hotkey) = other {
+                if prefix.overlaps(hotkey) {
+                    return Err(format!(
+                        "按键序列 \"{}\" 的前缀 \"{}\" 与热键 \"{}\" 冲突，存在歧义",
+                        trigger, prefix, hotkey
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// 解析 Tap 的参数: 按键[, Hold=按键]
+fn parse_tap(args: &[(&str, usize)], close_column: usize) -> Result<Trigger, HotkeyParseError> {
+    let Some(&(key_token, key_column)) = args.first().filter(|(token, _)| !token.is_empty()) else {
+        return Err(HotkeyParseError::MissingKey {
+            column: close_column,
+        });
+    };
+    let key = parse_trigger_key(key_token, key_column)?;
+
+    let mut hold = None;
+    for &(arg, column) in &args[1..] {
+        match arg.split_once('=') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("hold") => {
+                let value_column = column + arg.find('=').unwrap() + 1;
+                hold = Some(parse_trigger_key(value.trim(), value_column)?);
+            }
+            _ => {
+                return Err(HotkeyParseError::InvalidSyntax {
+                    column,
+                    message: "无法识别的参数，支持: Hold=按键",
+                })
+            }
+        }
+    }
+
+    Ok(Trigger::Tap { key, hold })
+}
+
+// 解析 DoubleTap 的参数: 按键
+fn parse_double_tap(
+    args: &[(&str, usize)],
+    close_column: usize,
+) -> Result<Trigger, HotkeyParseError> {
+    let Some(&(key_token, key_column)) = args.first().filter(|(token, _)| !token.is_empty()) else {
+        return Err(HotkeyParseError::MissingKey {
+            column: close_column,
+        });
+    };
+    if let Some(&(_, column)) = args.get(1) {
+        return Err(HotkeyParseError::InvalidSyntax {
+            column,
+            message: "DoubleTap 只接受一个按键",
+        });
+    }
+    let key = parse_trigger_key(key_token, key_column)?;
+    Ok(Trigger::DoubleTap { key })
+}
+
+// 键盘钩子触发方式中的按键，可以是单独的修饰键
+fn parse_trigger_key(token: &str, column: usize) -> Result<u32, HotkeyParseError> {
+    parse_any_key(token).ok_or_else(|| HotkeyParseError::UnknownKey {
+        token: token.to_string(),
+        column,
+        suggestion: closest_name(token, MODIFIER_KEY_TABLE)
+            .or_else(|| closest_name(token, KEY_TABLE)),
+    })
+}
+
+// 按逗号拆分 s[start..end]，返回去掉空白的参数及其列号
+fn split_args(s: &str, start: usize, end: usize) -> Vec<(&str, usize)> {
+    let mut args = Vec::new();
+    let mut offset = start;
+    for raw in s[start..end].split(',') {
+        let leading = raw.len() - raw.trim_start().len();
+        args.push((raw.trim(), column_of(s, offset + leading)));
+        offset += raw.len() + 1;
+    }
+    args
+}
+
+// 字节下标对应的列号（按字符计数，从 1 开始）
+fn column_of(s: &str, byte_index: usize) -> usize {
+    s[..byte_index].chars().count() + 1
+}
+
+fn key_display(code: u32) -> String {
+    match any_key_name(code) {
+        Some(name) => name.to_string(),
+        None => format!("VK_0x{:02X}", code),
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Hotkey(hotkey) => write!(f, "{}", hotkey),
+            Trigger::Tap { key, hold: None } => write!(f, "Tap({})", key_display(*key)),
+            Trigger::Tap {
+                key,
+                hold: Some(hold),
+            } => write!(f, "Tap({}, Hold={})", key_display(*key), key_display(*hold)),
+            Trigger::DoubleTap { key } => write!(f, "DoubleTap({})", key_display(*key)),
+            Trigger::Chord { prefix, key } => write!(f, "{}, {}", prefix, key),
+            Trigger::Mouse { modifiers, button } => write!(
+                f,
+                "{}{}",
+                modifiers_display(*modifiers),
+                mouse_button_name(*button)
+            ),
+        }
+    }
+}
+
+impl Serialize for Trigger {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Trigger {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|e| de::Error::custom(format!("无效的热键 \"{}\": {}", s, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(triggers: &[&str]) -> Result<(), String> {
+        let triggers: Vec<Trigger> = triggers.iter().map(|s| s.parse().unwrap()).collect();
+        check_chord_prefixes(&triggers)
+    }
+
+    #[test]
+    fn chord_prefix_conflicts_with_overlapping_hotkeys() {
+        assert_eq!(
+            check(&["Ctrl+K, E", "Ctrl+K"]),
+            Err(
+                "按键序列 \"Ctrl+K, E\" 的前缀 \"Ctrl+K\" 与热键 \"Ctrl+K\" 冲突，存在歧义"
+                    .to_string()
+            )
+        );
+        // 不区分左右的前缀与任意一侧的热键重叠
+        assert!(check(&["Ctrl+K, E", "LCtrl+K"]).is_err());
+        assert!(check(&["Ctrl+K, E", "RCtrl+K"]).is_err());
+        assert!(check(&["LCtrl+K, E", "Ctrl+K"]).is_err());
+    }
+
+    #[test]
+    fn chord_prefix_allows_distinct_hotkeys() {
+        assert_eq!(check(&["Ctrl+K, E", "Ctrl+Shift+K"]), Ok(()));
+        assert_eq!(check(&["Ctrl+K, E", "Alt+K", "K"]), Ok(()));
+        assert_eq!(check(&["LCtrl+K, E", "RCtrl+K"]), Ok(()));
+        // 同一前缀的多个按键序列
+        assert_eq!(check(&["Ctrl+K, E", "Ctrl+K, Ctrl+E"]), Ok(()));
+        // 其他触发方式不参与检查
+        assert_eq!(check(&["Ctrl+K, E", "Tap(K)", "DoubleTap(Ctrl)"]), Ok(()));
+    }
+
+    // (写法, 规范写法)：解析后按规范写法显示，规范写法解析回相同的触发方式
+    fn round_trip(cases: &[(&str, &str)]) {
+        for &(input, canonical) in cases {
+            let trigger: Trigger = input.parse().unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(trigger.to_string(), canonical, "{}", input);
+            assert_eq!(canonical.parse::<Trigger>(), Ok(trigger), "{}", canonical);
+        }
+    }
+
+    fn error(input: &str) -> HotkeyParseError {
+        input.parse::<Trigger>().unwrap_err()
+    }
+
+    #[test]
+    fn tap_round_trips() {
+        round_trip(&[
+            ("Ctrl+Shift+F", "Ctrl+Shift+F"),
+            ("Tap(LShift)", "Tap(LShift)"),
+            ("tap( lshift )", "Tap(LShift)"),
+            ("Tap(CapsLock, Hold=LCtrl)", "Tap(CapsLock, Hold=LCtrl)"),
+            ("TAP(capslock,hold = lctrl)", "Tap(CapsLock, Hold=LCtrl)"),
+            ("Tap(F13)", "Tap(F13)"),
+        ]);
+        assert_eq!(
+            "Tap(CapsLock, Hold=LCtrl)".parse::<Trigger>(),
+            Ok(Trigger::Tap {
+                key: 0x14,
+                hold: Some(0xA2)
+            })
+        );
+    }
+
+    #[test]
+    fn tap_errors() {
+        assert_eq!(
+            error("Tap(LShift"),
+            HotkeyParseError::InvalidSyntax {
+                column: 11,
+                message: "缺少右括号 \")\"",
+            }
+        );
+        assert_eq!(error("Tap()"), HotkeyParseError::MissingKey { column: 5 });
+        assert_eq!(
+            error("Tap(A, Repeat=B)"),
+            HotkeyParseError::InvalidSyntax {
+                column: 8,
+                message: "无法识别的参数，支持: Hold=按键",
+            }
+        );
+        assert_eq!(
+            error("Tap(LShfit)"),
+            HotkeyParseError::UnknownKey {
+                token: "LShfit".to_string(),
+                column: 5,
+                suggestion: Some("LShift"),
+            }
+        );
+        assert_eq!(
+            error("Tap(CapsLock, Hold=Ctl)").to_string(),
+            "第 20 列的按键 \"Ctl\" 无法识别，是否为 \"Ctrl\"？"
+        );
+        assert!(matches!(
+            error("Press(A)"),
+            HotkeyParseError::InvalidSyntax { column: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn double_tap_round_trips() {
+        round_trip(&[
+            ("DoubleTap(Ctrl)", "DoubleTap(Ctrl)"),
+            ("doubletap( rshift )", "DoubleTap(RShift)"),
+        ]);
+        assert_eq!(
+            error("DoubleTap(Ctrl, Shift)"),
+            HotkeyParseError::InvalidSyntax {
+                column: 17,
+                message: "DoubleTap 只接受一个按键",
+            }
+        );
+        assert_eq!(
+            error("DoubleTap( )"),
+            HotkeyParseError::MissingKey { column: 12 }
+        );
+        assert_eq!(
+            error("Hold(A)"),
+            HotkeyParseError::InvalidSyntax {
+                column: 1,
+                message: "未知的触发方式，支持: Tap, DoubleTap",
+            }
+        );
+    }
+
+    #[test]
+    fn chord_round_trips() {
+        round_trip(&[
+            ("Ctrl+K, E", "Ctrl+K, E"),
+            ("ctrl+k,ctrl+e", "Ctrl+K, Ctrl+E"),
+            ("Ctrl+,, E", "Ctrl+Comma, E"),
+            ("Ctrl+K, ,", "Ctrl+K, Comma"),
+        ]);
+        assert_eq!(
+            error("Ctrl+K, E, F"),
+            HotkeyParseError::InvalidSyntax {
+                column: 10,
+                message: "按键序列最多支持两段，如 \"Ctrl+K, E\"",
+            }
+        );
+        assert_eq!(
+            error("Ctrl+K, "),
+            HotkeyParseError::MissingKey { column: 9 }
+        );
+        // 第二段的错误列号按整个字符串计算
+        assert_eq!(
+            error("Ctrl+K, Ctl+E"),
+            HotkeyParseError::UnknownModifier {
+                token: "Ctl".to_string(),
+                column: 9,
+                suggestion: Some("Ctrl"),
+            }
+        );
+    }
+
+    #[test]
+    fn mouse_round_trips() {
+        round_trip(&[
+            ("XButton1", "XButton1"),
+            ("ctrl+mbutton", "Ctrl+MButton"),
+            ("Shift+WheelLeft", "Shift+WheelLeft"),
+        ]);
+        assert!(matches!(
+            "XButton2".parse::<Trigger>(),
+            Ok(Trigger::Mouse { modifiers: 0, .. })
+        ));
+        // 拼错的鼠标按键给出建议
+        assert_eq!(
+            error("Ctrl+XButon1"),
+            HotkeyParseError::UnknownKey {
+                token: "XButon1".to_string(),
+                column: 6,
+                suggestion: Some("XButton1"),
+            }
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use windows::core::PWSTR;
+use windows::Win32::{
+    Foundation::{CloseHandle, HWND},
+    System::Threading::*,
+    UI::WindowsAndMessaging::GetWindowThreadProcessId,
+};
+
+// 获取窗口所属进程的可执行文件名（如 "WeChat.exe"）
+pub fn process_name_of_window(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buf = [0u16; 260];
+        let mut size = buf.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buf.as_mut_ptr()),
+            &mut size,
+        );
+        let _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buf[..size as usize]);
+        path.rsplit('\\').next().map(|name| name.to_string())
+    }
+}